@@ -0,0 +1,61 @@
+use std::time::Instant;
+use sudoku::*;
+
+// Fixed corpus of well known 9x9 puzzles, with '.' for an empty tile
+const CORPUS: [(&str, &str); 5] = [
+    ("easy", ".1...2...3.456.1.7.8..1...9.37....9..9..4..1..5....82.5...7..6.2.1.935.8...1...4."),
+    ("ai_escargot", "1....7.9..3..2...8..96..5....53..9...1..8...26....4...3......1..4......7..7...3.."),
+    ("inkala_2012", "8..........36......7..9.2...5...7.......457.....1...3...1....68..85...1..9....4.."),
+    ("top95_1", "4.....8.5.3..........7......2.....6.....8.4......1.......6.3.7.5..2.....1.4......"),
+    ("easter_monster", "1.......2.9.4...5...6...7...5.9.3.......7.......85..4.7.....6...3...9.8...2.....1"),
+];
+
+fn parse(puzzle: &str) -> SudokuBoard<3> {
+    let array: Vec<usize> = puzzle
+        .chars()
+        .map(|c| c.to_digit(10).unwrap_or(0) as usize)
+        .collect();
+    SudokuBoard::<3>::from_array(&array).unwrap()
+}
+
+// Runs f the given number of times and prints the mean time per run
+fn bench(name: &str, iterations: u32, mut f: impl FnMut()) {
+    f();
+    let start = Instant::now();
+    for _ in 0..iterations {
+        f();
+    }
+    let per_run = start.elapsed() / iterations;
+    println!("{:<56} {:>12.3?}", name, per_run);
+}
+
+fn main() {
+    for (name, puzzle) in CORPUS {
+        let board = parse(puzzle);
+        bench(&format!("solve_csp/{}", name), 20, || {
+            let mut board = board.clone();
+            assert!(board.solve_csp().unwrap().is_solved());
+        });
+        for propagation in &Propagation::ALL[1..] {
+            let options = SolverOptions { propagation: *propagation, ..SolverOptions::default() };
+            let steps = board.clone().solve_csp_with(&options, &mut MinimumRemainingValues, &mut ()).unwrap().nodes;
+            let label = format!("solve_csp/{:?}/{} ({} nodes)", propagation, name, steps);
+            bench(&label, 20, || {
+                let mut board = board.clone();
+                assert!(board.solve_csp_with(&options, &mut MinimumRemainingValues, &mut ()).unwrap().is_solved());
+            });
+        }
+        bench(&format!("count_solutions/{}", name), 20, || {
+            assert_eq!(board.count_solutions(2), 1);
+        });
+        bench(&format!("solve_dlx/{}", name), 20, || {
+            let mut board = board.clone();
+            assert!(board.solve_dlx().unwrap().is_solved());
+        });
+    }
+
+    let empty = SudokuBoard::<2>::from_array(&[0; 16]).unwrap();
+    bench("count_solutions/empty_4x4", 200, || {
+        assert_eq!(empty.count_solutions(usize::MAX), 288);
+    });
+}
@@ -7,10 +7,9 @@ const TEST_BOARD: [usize; SUDOKU_SIZE * SUDOKU_SIZE] = [
 ];
 
 fn main() {
-    let mut board = SudokuBoard::from_array(TEST_BOARD);
+    let mut board = SudokuBoard::<3>::from_array(&TEST_BOARD).unwrap();
 
-    let mut steps = 0;
-    board.solve_brute_force(150, &mut steps);
+    let steps = board.solve_brute_force_with(&Budget::default(), &mut TerminalAnimation::new(150)).unwrap().nodes;
 
     println!("Solved in {} steps", steps);
 }
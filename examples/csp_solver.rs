@@ -10,12 +10,14 @@ fn main() {
 
     let delay_ms = 50;
 
-    let mut csp_board = SudokuBoard::from_array(TEST_BOARD);
-    let mut csp_steps = 0;
-    csp_board.solve_csp(delay_ms, &mut csp_steps);
+    let mut csp_board = SudokuBoard::<3>::from_array(&TEST_BOARD).unwrap();
+    let mut animation = TerminalAnimation::new(delay_ms);
+    let csp_steps = csp_board
+        .solve_csp_with(&SolverOptions::default(), &mut MinimumRemainingValues, &mut animation)
+        .unwrap()
+        .nodes;
 
-    let mut dfs_board = SudokuBoard::from_array(TEST_BOARD);
-    let mut dfs_steps = 0;
-    dfs_board.solve_brute_force(delay_ms, &mut dfs_steps);
+    let mut dfs_board = SudokuBoard::<3>::from_array(&TEST_BOARD).unwrap();
+    let dfs_steps = dfs_board.solve_brute_force_with(&Budget::default(), &mut animation).unwrap().nodes;
     println!("CSP solved in {} steps\nDFS solved in {} steps", csp_steps, dfs_steps);
 }
\ No newline at end of file
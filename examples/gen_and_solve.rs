@@ -6,13 +6,16 @@ fn main() {
   let cmdline_args: Vec<String> = std::env::args().collect();
 
   let animation_delay_ms = match cmdline_args.len() {
-    2 => u64::from_str_radix(cmdline_args[1].as_str(), 10).unwrap(),
+    2 => cmdline_args[1].parse::<u64>().unwrap(),
     _ => 50,
   };
 
-  let mut board = SudokuBoard::generate(31);
-  let mut steps = 0;
-  board.solve_csp(animation_delay_ms, &mut steps);
+  let mut board = SudokuBoard::<3>::generate(31).unwrap();
+  let mut animation = TerminalAnimation::new(animation_delay_ms);
+  let steps = board
+    .solve_csp_with(&SolverOptions::default(), &mut MinimumRemainingValues, &mut animation)
+    .unwrap()
+    .nodes;
   println!("Solved in {} steps", steps);
   
 }
\ No newline at end of file
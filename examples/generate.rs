@@ -1,6 +1,6 @@
 use sudoku::*;
 
 fn main() {
-    let board = SudokuBoard::generate(21);
+    let board = SudokuBoard::<3>::generate(21).unwrap();
     board.print(false);
 }
\ No newline at end of file
@@ -1,63 +1,119 @@
-use rand::random;
+use std::fmt;
+use std::time::Instant;
 
-use crate::tile::{Domain, Tile};
+use rand::Rng;
+
+use crate::error::SudokuError;
+use crate::generator::{GeneratorOptions, SolutionSource, Symmetry};
+use crate::grade::Difficulty;
+use crate::observer::SolverObserver;
+use crate::prng::{below, shuffle, SplitMix64};
+use crate::solver::{Branch, Budget, CspSearch, Meter, SolveOutcome, SolveStats, SolverOptions};
+use crate::strategy::{MinimumRemainingValues, RandomOrder, SearchStrategy};
+use crate::tile::{Domain, DomainIter, Tile};
 
 pub const SUDOKU_BASE: usize = 3;
 pub const SUDOKU_SIZE: usize = SUDOKU_BASE * SUDOKU_BASE;
 
+// Largest board side supported by Domain and print (6x6 boxes)
+pub const MAX_SIZE: usize = 36;
+
+// Tiles as they were before each change a search made, most recent last, so
+// backtracking can put them back exactly
+pub(crate) type Trail<const BOX_ROWS: usize, const BOX_COLS: usize> = Vec<(usize, usize, Tile<BOX_ROWS, BOX_COLS>)>;
+
+// Boxes are BOX_ROWS tall and BOX_COLS wide, so SudokuBoard<3> is the classic
+// 9x9 board and SudokuBoard<2, 3> is a 6x6 board with 2x3 boxes
 #[derive(Clone)]
-pub struct SudokuBoard {
-    board: [[Tile; SUDOKU_SIZE]; SUDOKU_SIZE],
+pub struct SudokuBoard<const BOX_ROWS: usize = SUDOKU_BASE, const BOX_COLS: usize = BOX_ROWS> {
+    pub(crate) board: Vec<Vec<Tile<BOX_ROWS, BOX_COLS>>>,
 }
 
-impl SudokuBoard {
-    pub fn from_array(array: [usize; SUDOKU_SIZE * SUDOKU_SIZE]) -> Self {
-        let mut board = [[Tile::default(); SUDOKU_SIZE]; SUDOKU_SIZE];
-        for row in 0..SUDOKU_SIZE {
-            for col in 0..SUDOKU_SIZE {
-                let index = row * SUDOKU_SIZE + col;
-                match array[index] {
-                    0 => {}
-                    c => {
-                        board[row][col] = Tile::Collapsed(c);
-                    },
-                }
-            }
+// A row, column or box, each of which must hold every value exactly once.
+// Boxes are numbered left to right, top to bottom.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Unit {
+    Row(usize),
+    Col(usize),
+    Box(usize),
+}
+
+impl fmt::Display for Unit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Unit::Row(row) => write!(f, "row {}", row + 1),
+            Unit::Col(col) => write!(f, "column {}", col + 1),
+            Unit::Box(b) => write!(f, "box {}", b + 1),
         }
+    }
+}
 
-        let mut board = Self { board };
-        board.propagate_uncollapsed();
-        board
+impl<const BOX_ROWS: usize, const BOX_COLS: usize> SudokuBoard<BOX_ROWS, BOX_COLS> {
+    pub const SIZE: usize = {
+        assert!(BOX_ROWS >= 1 && BOX_COLS >= 1, "boxes must be at least 1x1");
+        assert!(
+            BOX_ROWS * BOX_COLS >= 2 && BOX_ROWS * BOX_COLS <= MAX_SIZE,
+            "board size must be between 2 and MAX_SIZE"
+        );
+        BOX_ROWS * BOX_COLS
+    };
+
+    fn empty() -> Self {
+        Self {
+            board: vec![vec![Tile::default(); Self::SIZE]; Self::SIZE],
+        }
     }
 
-    pub fn into_array(&self) -> [usize; SUDOKU_SIZE*SUDOKU_SIZE] {
-        let mut array = [0usize; SUDOKU_SIZE*SUDOKU_SIZE];
-        for row in 0..SUDOKU_SIZE {
-            for col in 0..SUDOKU_SIZE {
-                let index = row*SUDOKU_SIZE + col;
-                array[index] = match self.board[row][col] {
-                    Tile::Collapsed(val) => val,
-                    Tile::Uncollapsed(_) => 0,
+    // Expects SIZE*SIZE values in row-major order, 0 for an empty tile
+    pub fn from_array(array: &[usize]) -> Result<Self, SudokuError> {
+        if array.len() != Self::SIZE * Self::SIZE {
+            return Err(SudokuError::WrongLength {
+                expected: Self::SIZE * Self::SIZE,
+                found: array.len(),
+            });
+        }
+
+        let mut board = Self::empty();
+        for (index, &val) in array.iter().enumerate() {
+            let (row, col) = (index / Self::SIZE, index % Self::SIZE);
+            match val {
+                0 => {}
+                val if val > Self::SIZE => {
+                    return Err(SudokuError::InvalidTile { row, col, value: val });
                 }
+                val => board.board[row][col] = Tile::Collapsed(val),
             }
         }
-        array
+
+        board.propagate_uncollapsed();
+        Ok(board)
+    }
+
+    pub fn into_array(&self) -> Vec<usize> {
+        self.board
+            .iter()
+            .flatten()
+            .map(|tile| match tile {
+                Tile::Collapsed(val) => *val,
+                Tile::Uncollapsed(_) => 0,
+            })
+            .collect()
     }
-    
-    fn add_holes(&mut self, num_filled: usize) {
 
-        let num_holes = SUDOKU_SIZE*SUDOKU_SIZE - num_filled;
-        
-        for _ in 0..num_holes { 
-            let mut row = random::<usize>()%SUDOKU_SIZE;
-            let mut col = random::<usize>()%SUDOKU_SIZE;
+    fn add_holes(&mut self, num_filled: usize, rng: &mut impl Rng) {
+
+        let num_holes = Self::SIZE*Self::SIZE - num_filled;
+
+        for _ in 0..num_holes {
+            let mut row = below(rng, Self::SIZE);
+            let mut col = below(rng, Self::SIZE);
             let mut valid = false;
             while !valid {
                 if let Tile::Collapsed(_) = &self.board[row][col] {
                     valid = true;
                 } else {
-                    row = random::<usize>()%SUDOKU_SIZE;
-                    col = random::<usize>()%SUDOKU_SIZE;
+                    row = below(rng, Self::SIZE);
+                    col = below(rng, Self::SIZE);
                 }
             }
             self.board[row][col] = Tile::default();
@@ -65,28 +121,597 @@ impl SudokuBoard {
 
     }
 
-    pub fn generate(num_filled: usize) -> Self {
-        let mut board = [[Tile::default(); SUDOKU_SIZE]; SUDOKU_SIZE];
-        
-        let row: usize = rand::random::<usize>()%SUDOKU_SIZE;
-        let col: usize = rand::random::<usize>()%SUDOKU_SIZE;
-        let val: usize = rand::random::<usize>()%SUDOKU_SIZE + 1;
-        
-        board[row][col] = Tile::Collapsed(val);
-        
-        let mut sudoku_board = Self { board };
+    // Like add_holes, but tiles are removed a symmetry orbit at a time, and
+    // only if the puzzle keeps a unique solution, still satisfies accept and
+    // has at least num_filled tiles left. Returns false if no more tiles could
+    // be removed before reaching num_filled, or if meter ran out first.
+    fn add_holes_unique(
+        &mut self,
+        num_filled: usize,
+        symmetry: Symmetry,
+        accept: impl Fn(&Self) -> bool,
+        meter: &mut Meter,
+        rng: &mut impl Rng,
+    ) -> bool {
+        let mut orbits = Self::orbits(symmetry);
+        shuffle(rng, &mut orbits);
+
+        let mut filled = self.into_array().iter().filter(|&&val| val != 0).count();
+        for orbit in orbits {
+            if filled <= num_filled {
+                break;
+            }
+            let (row, col) = orbit[0];
+            if let Tile::Uncollapsed(_) = self.board[row][col] {
+                continue;
+            }
+            if filled - orbit.len() < num_filled {
+                continue;
+            }
+
+            let tiles: Vec<Tile<BOX_ROWS, BOX_COLS>> = orbit.iter().map(|&(r, c)| self.board[r][c]).collect();
+            for &(r, c) in &orbit {
+                self.board[r][c] = Tile::default();
+            }
+            self.reset_domains();
+            let unique = self.count_solutions_metered(2, meter) == 1;
+            if !meter.aborted && unique && accept(self) {
+                filled -= orbit.len();
+            } else {
+                for (&(r, c), &tile) in orbit.iter().zip(&tiles) {
+                    self.board[r][c] = tile;
+                }
+            }
+            if meter.aborted {
+                break;
+            }
+        }
+        self.reset_domains();
+
+        filled <= num_filled
+    }
+
+    // A full board: one random tile, then the first solution solve_csp finds
+    fn generate_first_solution(rng: &mut impl Rng) -> Self {
+        let mut sudoku_board = Self::empty();
+
+        let row = below(rng, Self::SIZE);
+        let col = below(rng, Self::SIZE);
+        let val = below(rng, Self::SIZE) + 1;
+
+        sudoku_board.board[row][col] = Tile::Collapsed(val);
+
         sudoku_board.propagate_uncollapsed();
-        sudoku_board.solve_csp(0, &mut 0);
-        sudoku_board.add_holes(num_filled);
-        
+        sudoku_board.solve_csp().expect("a single filled tile is valid");
+        sudoku_board
+    }
+
+    // A full board, made by shuffling a pattern. Fast, but only reaches boards
+    // where each band's box rows hold the same few sets of values.
+    pub fn generate_solution() -> Self {
+        Self::generate_solution_with_rng(&mut rand::thread_rng())
+    }
+
+    // Adapted from gen_board in sudoku.ipynb: a baseline valid solution with
+    // bands, rows within bands, stacks, columns within stacks and values shuffled
+    pub fn generate_solution_with_rng(rng: &mut impl Rng) -> Self {
+        let mut shuffled = |n: usize| {
+            let mut values: Vec<usize> = (0..n).collect();
+            shuffle(rng, &mut values);
+            values
+        };
+
+        let mut rows = Vec::with_capacity(Self::SIZE);
+        let mut cols = Vec::with_capacity(Self::SIZE);
+        for g in shuffled(BOX_COLS) {
+            rows.extend(shuffled(BOX_ROWS).into_iter().map(|r| g*BOX_ROWS + r));
+        }
+        for g in shuffled(BOX_ROWS) {
+            cols.extend(shuffled(BOX_COLS).into_iter().map(|c| g*BOX_COLS + c));
+        }
+        let nums = shuffled(Self::SIZE);
+
+        let pattern = |r: usize, c: usize| (BOX_COLS*(r%BOX_ROWS) + r/BOX_ROWS + c) % Self::SIZE;
+
+        let mut sudoku_board = Self::empty();
+        for (row, &r) in rows.iter().enumerate() {
+            for (col, &c) in cols.iter().enumerate() {
+                sudoku_board.board[row][col] = Tile::Collapsed(nums[pattern(r, c)] + 1);
+            }
+        }
+        sudoku_board
+    }
+
+    // A full board found by solving an empty one in a random order. Much
+    // slower than generate_solution, but can reach any board.
+    pub fn generate_solution_by_search() -> Self {
+        Self::generate_solution_by_search_with_rng(&mut rand::thread_rng())
+    }
+
+    pub fn generate_solution_by_search_with_rng(rng: &mut impl Rng) -> Self {
+        let mut sudoku_board = Self::empty();
+        let mut strategy = RandomOrder::new(rng.next_u64());
+        sudoku_board
+            .solve_csp_with(&SolverOptions::default(), &mut strategy, &mut ())
+            .expect("an empty board is valid");
         sudoku_board
     }
 
+    // A full board from source
+    fn generate_solution_from(source: SolutionSource, rng: &mut impl Rng) -> Self {
+        match source {
+            SolutionSource::FirstSolution => Self::generate_first_solution(rng),
+            SolutionSource::Pattern => Self::generate_solution_with_rng(rng),
+            SolutionSource::Search => Self::generate_solution_by_search_with_rng(rng),
+        }
+    }
+
+    pub fn generate(num_filled: usize) -> Result<Self, SudokuError> {
+        Self::generate_with_rng(num_filled, &mut rand::thread_rng())
+    }
+
+    // generate, taking every random choice from rng, so a seeded rng gives
+    // the same puzzle each time
+    pub fn generate_with_rng(num_filled: usize, rng: &mut impl Rng) -> Result<Self, SudokuError> {
+        Self::generate_with_source(num_filled, SolutionSource::FirstSolution, rng)
+    }
+
+    // generate_with_rng, emptying tiles of a full board from source
+    pub fn generate_with_source(
+        num_filled: usize,
+        source: SolutionSource,
+        rng: &mut impl Rng,
+    ) -> Result<Self, SudokuError> {
+        if num_filled > Self::SIZE * Self::SIZE {
+            return Err(SudokuError::TooManyFilled {
+                requested: num_filled,
+                max: Self::SIZE * Self::SIZE,
+            });
+        }
+
+        let mut sudoku_board = Self::generate_solution_from(source, rng);
+        sudoku_board.add_holes(num_filled, rng);
+        sudoku_board.propagate_uncollapsed();
+
+        Ok(sudoku_board)
+    }
+
+    // Puzzle number seed: generate with a SplitMix64 seeded with seed. The
+    // same seed gives the same puzzle in every version of this crate.
+    pub fn generate_seeded(num_filled: usize, seed: u64) -> Result<Self, SudokuError> {
+        Self::generate_with_rng(num_filled, &mut SplitMix64::new(seed))
+    }
+
+    // Generates a puzzle with exactly one solution. Returns the puzzle and
+    // whether it got down to num_filled tiles; if not, it is the sparsest
+    // unique puzzle that was reached.
+    pub fn generate_unique(num_filled: usize) -> Result<(Self, bool), SudokuError> {
+        Self::generate_unique_within(num_filled, &Budget::default())
+    }
+
+    // generate_unique, or Err(SudokuError::Aborted) if budget runs out first.
+    // The nodes of every uniqueness check count towards budget.
+    pub fn generate_unique_within(num_filled: usize, budget: &Budget) -> Result<(Self, bool), SudokuError> {
+        let options = GeneratorOptions {
+            budget: budget.clone(),
+            ..GeneratorOptions::default()
+        };
+        Self::generate_unique_with(num_filled, &options, &mut rand::thread_rng())
+    }
+
+    // generate_unique_within with the given settings, taking every random
+    // choice from rng. With a symmetry, tiles are only removed in whole
+    // orbits, so the puzzle is more likely to stop short of num_filled.
+    pub fn generate_unique_with(
+        num_filled: usize,
+        options: &GeneratorOptions,
+        rng: &mut impl Rng,
+    ) -> Result<(Self, bool), SudokuError> {
+        if num_filled > Self::SIZE * Self::SIZE {
+            return Err(SudokuError::TooManyFilled {
+                requested: num_filled,
+                max: Self::SIZE * Self::SIZE,
+            });
+        }
+
+        let mut meter = Meter::new(&options.budget);
+        let mut sudoku_board = Self::generate_solution_from(options.source, rng);
+        let reached = sudoku_board.add_holes_unique(num_filled, options.symmetry, |_| true, &mut meter, rng);
+        match meter.aborted {
+            true => Err(SudokuError::Aborted),
+            false => Ok((sudoku_board, reached)),
+        }
+    }
+
+    // Generates a puzzle that is_minimal, with as many tiles as that takes
+    pub fn generate_minimal() -> Self {
+        match Self::generate_minimal_with(&GeneratorOptions::default(), &mut rand::thread_rng()) {
+            Ok(generated) => generated,
+            Err(_) => unreachable!("an unlimited budget never runs out"),
+        }
+    }
+
+    // generate_minimal with the given settings, taking every random choice
+    // from rng, or Err(SudokuError::Aborted) if the budget runs out first.
+    // With a symmetry, no orbit can be emptied without losing uniqueness, but
+    // a single tile of one might.
+    pub fn generate_minimal_with(options: &GeneratorOptions, rng: &mut impl Rng) -> Result<Self, SudokuError> {
+        let mut meter = Meter::new(&options.budget);
+        let mut sudoku_board = Self::generate_solution_from(options.source, rng);
+        // Emptying tiles only adds solutions, so a tile that could not go
+        // when it was tried still cannot once the pass is over
+        sudoku_board.add_holes_unique(0, options.symmetry, |_| true, &mut meter, rng);
+        match meter.aborted {
+            true => Err(SudokuError::Aborted),
+            false => Ok(sudoku_board),
+        }
+    }
+
+    // Generates a puzzle whose filled tiles are exactly those where mask,
+    // given row by row, is true, and which has a unique solution. Gives up
+    // with None after trying max_attempts solutions.
+    pub fn generate_from_mask(mask: &[bool], max_attempts: usize) -> Result<Option<Self>, SudokuError> {
+        Self::generate_from_mask_with(mask, max_attempts, &Budget::default(), &mut rand::thread_rng())
+    }
+
+    // generate_from_mask, taking every random choice from rng, or
+    // Err(SudokuError::Aborted) if budget runs out first
+    pub fn generate_from_mask_with(
+        mask: &[bool],
+        max_attempts: usize,
+        budget: &Budget,
+        rng: &mut impl Rng,
+    ) -> Result<Option<Self>, SudokuError> {
+        if mask.len() != Self::SIZE * Self::SIZE {
+            return Err(SudokuError::WrongLength {
+                expected: Self::SIZE * Self::SIZE,
+                found: mask.len(),
+            });
+        }
+        // Solutions counted per attempt, enough to tell a nearly unique
+        // puzzle from a hopeless one
+        const LIMIT: usize = 16;
+
+        let mut meter = Meter::new(budget);
+        // The solution whose masked puzzle has the fewest solutions so far,
+        // and how many attempts since that last went down
+        let mut best: Option<(Self, usize)> = None;
+        let mut stalled = 0;
+        for _ in 0..max_attempts {
+            // Swapping two rows of a band or two columns of a stack keeps the
+            // solution valid but moves its values under the mask
+            let solution = match &best {
+                Some((solution, _)) if stalled < Self::SIZE => {
+                    let mut solution = solution.clone();
+                    solution.swap_lines(rng);
+                    solution
+                }
+                _ => {
+                    best = None;
+                    stalled = 0;
+                    Self::generate_solution_by_search_with_rng(rng)
+                }
+            };
+
+            let mut puzzle = solution.clone();
+            for (i, &given) in mask.iter().enumerate() {
+                if !given {
+                    puzzle.board[i / Self::SIZE][i % Self::SIZE] = Tile::default();
+                }
+            }
+            puzzle.reset_domains();
+            let count = puzzle.count_solutions_metered(LIMIT, &mut meter);
+            if meter.aborted {
+                return Err(SudokuError::Aborted);
+            }
+            if count == 1 {
+                return Ok(Some(puzzle));
+            }
+
+            match &best {
+                Some((_, fewest)) if count > *fewest => stalled += 1,
+                Some((_, fewest)) if count == *fewest => {
+                    stalled += 1;
+                    best = Some((solution, count));
+                }
+                _ => {
+                    stalled = 0;
+                    best = Some((solution, count));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    // Swaps two random rows within a band, or two columns within a stack
+    fn swap_lines(&mut self, rng: &mut impl Rng) {
+        let rows = BOX_ROWS >= 2 && (BOX_COLS < 2 || below(rng, 2) == 0);
+        let (group, across) = match rows {
+            true => (BOX_ROWS, BOX_COLS),
+            false => (BOX_COLS, BOX_ROWS),
+        };
+        let first = below(rng, across) * group;
+        let a = first + below(rng, group);
+        let b = first + (a - first + 1 + below(rng, group - 1)) % group;
+        match rows {
+            true => self.board.swap(a, b),
+            false => {
+                for row in self.board.iter_mut() {
+                    row.swap(a, b);
+                }
+            }
+        }
+    }
+
+    // Generates unique puzzles until one grades as target, giving up after
+    // max_attempts puzzles. Each puzzle has as many tiles removed as possible
+    // without becoming harder than target. Tiers hold scores in [min, max), so
+    // a score at target's upper bound is already too hard.
+    pub fn generate_with_difficulty(target: Difficulty, max_attempts: usize) -> Option<Self> {
+        match Self::generate_with_difficulty_within(target, max_attempts, &Budget::default()) {
+            Ok(generated) => generated,
+            Err(_) => unreachable!("an unlimited budget never runs out"),
+        }
+    }
+
+    // generate_with_difficulty, or Err(SudokuError::Aborted) if budget runs
+    // out first. The nodes of every uniqueness check count towards budget.
+    pub fn generate_with_difficulty_within(
+        target: Difficulty,
+        max_attempts: usize,
+        budget: &Budget,
+    ) -> Result<Option<Self>, SudokuError> {
+        let options = GeneratorOptions {
+            budget: budget.clone(),
+            ..GeneratorOptions::default()
+        };
+        Self::generate_with_difficulty_with(target, max_attempts, &options, &mut rand::thread_rng())
+    }
+
+    // generate_with_difficulty_within with the given settings, taking every
+    // random choice from rng
+    pub fn generate_with_difficulty_with(
+        target: Difficulty,
+        max_attempts: usize,
+        options: &GeneratorOptions,
+        rng: &mut impl Rng,
+    ) -> Result<Option<Self>, SudokuError> {
+        let mut meter = Meter::new(&options.budget);
+        for _ in 0..max_attempts {
+            let mut sudoku_board = Self::generate_solution_from(options.source, rng);
+            let accept = |board: &Self| board.grades_at_most(target);
+            sudoku_board.add_holes_unique(0, options.symmetry, accept, &mut meter, rng);
+            if meter.aborted {
+                return Err(SudokuError::Aborted);
+            }
+            if sudoku_board.grade().difficulty == target {
+                return Ok(Some(sudoku_board));
+            }
+        }
+        Ok(None)
+    }
+
+    pub fn has_unique_solution(&self) -> bool {
+        self.count_solutions(2) == 1
+    }
+
+    // True if the puzzle has a unique solution and loses it when any one of
+    // its filled tiles is emptied
+    pub fn is_minimal(&self) -> bool {
+        match self.is_minimal_within(&Budget::default()) {
+            Ok(minimal) => minimal,
+            Err(_) => unreachable!("an unlimited budget never runs out"),
+        }
+    }
+
+    // is_minimal, or Err(SudokuError::Aborted) if budget runs out first. The
+    // nodes of every uniqueness check count towards budget.
+    pub fn is_minimal_within(&self, budget: &Budget) -> Result<bool, SudokuError> {
+        let mut meter = Meter::new(budget);
+        let mut unique = |puzzle: &Self| match puzzle.count_solutions_metered(2, &mut meter) {
+            _ if meter.aborted => Err(SudokuError::Aborted),
+            count => Ok(count == 1),
+        };
+        if !unique(self)? {
+            return Ok(false);
+        }
+        let mut puzzle = self.clone();
+        for row in 0..Self::SIZE {
+            for col in 0..Self::SIZE {
+                let tile = puzzle.board[row][col];
+                if let Tile::Uncollapsed(_) = tile {
+                    continue;
+                }
+                puzzle.board[row][col] = Tile::default();
+                puzzle.reset_domains();
+                let still_unique = unique(&puzzle)?;
+                puzzle.board[row][col] = tile;
+                if still_unique {
+                    return Ok(false);
+                }
+            }
+        }
+        Ok(true)
+    }
+
+    // Counts solutions up to limit, so count_solutions(2) is enough to tell
+    // whether a puzzle is unique
+    pub fn count_solutions(&self, limit: usize) -> usize {
+        match self.count_solutions_within(limit, &Budget::default()) {
+            Ok(count) => count,
+            Err(_) => unreachable!("an unlimited budget never runs out"),
+        }
+    }
+
+    // count_solutions, or Err(SudokuError::Aborted) if budget runs out first
+    pub fn count_solutions_within(&self, limit: usize, budget: &Budget) -> Result<usize, SudokuError> {
+        let mut meter = Meter::new(budget);
+        let count = self.count_solutions_metered(limit, &mut meter);
+        match meter.aborted {
+            true => Err(SudokuError::Aborted),
+            false => Ok(count),
+        }
+    }
+
+    // count_solutions, giving up with a meaningless count once meter runs out
+    fn count_solutions_metered(&self, limit: usize, meter: &mut Meter) -> usize {
+        if !self.is_valid() {
+            return 0;
+        }
+        self.clone().count_solutions_csp(limit, &mut Trail::new(), meter)
+    }
+
+    // Lazily enumerates every solution, each one once and in no promised order
+    pub fn solutions(&self) -> Solutions<BOX_ROWS, BOX_COLS> {
+        let stack = match self.is_valid() {
+            true => vec![self.clone()],
+            false => Vec::new(),
+        };
+        Solutions { stack }
+    }
+
+    // Values that can still go in an empty tile, or nothing if it is filled
+    pub fn candidates(&self, row: usize, col: usize) -> Vec<usize> {
+        match self.board[row][col] {
+            Tile::Collapsed(_) => Vec::new(),
+            Tile::Uncollapsed(domain) => domain.get_valid(),
+        }
+    }
+
+    // The value in a tile, or None if it is empty. Panics if the tile is off
+    // the board, like indexing a slice.
+    pub fn get(&self, row: usize, col: usize) -> Option<usize> {
+        match self.board[row][col] {
+            Tile::Collapsed(val) => Some(val),
+            Tile::Uncollapsed(_) => None,
+        }
+    }
+
+    // Fills a tile, replacing any value already there. The value may break the
+    // rules, as a player's guess can, which is_valid will report.
+    pub fn set(&mut self, row: usize, col: usize, value: usize) -> Result<(), SudokuError> {
+        self.check_bounds(row, col)?;
+        if value == 0 || value > Self::SIZE {
+            return Err(SudokuError::ValueOutOfRange { value, size: Self::SIZE });
+        }
+
+        self.clear(row, col)?;
+        self.board[row][col] = Tile::Collapsed(value);
+        self.propagate_collapse(row, col);
+        Ok(())
+    }
+
+    // Empties a tile and gives its value back to the peers that no other
+    // filled tile rules it out for
+    pub fn clear(&mut self, row: usize, col: usize) -> Result<(), SudokuError> {
+        self.check_bounds(row, col)?;
+        let val = match self.board[row][col] {
+            Tile::Collapsed(val) => val,
+            Tile::Uncollapsed(_) => return Ok(()),
+        };
+
+        self.board[row][col] = Tile::Uncollapsed(self.peer_domain(row, col));
+        for (r, c) in Self::peers(row, col) {
+            let blocked = Self::peers(r, c)
+                .into_iter()
+                .any(|(pr, pc)| self.get(pr, pc) == Some(val));
+            if let Tile::Uncollapsed(domain) = &mut self.board[r][c] {
+                if !blocked {
+                    domain.mark_valid(val);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn check_bounds(&self, row: usize, col: usize) -> Result<(), SudokuError> {
+        match row < Self::SIZE && col < Self::SIZE {
+            true => Ok(()),
+            false => Err(SudokuError::OutOfBounds { row, col }),
+        }
+    }
+
+    // Every other tile sharing a row, column or box with (row, col)
+    fn peers(row: usize, col: usize) -> Vec<(usize, usize)> {
+        let mut peers: Vec<(usize, usize)> = [
+            Unit::Row(row),
+            Unit::Col(col),
+            Unit::Box(Self::box_index(row, col)),
+        ]
+        .into_iter()
+        .flat_map(Self::unit_cells)
+        .filter(|&tile| tile != (row, col))
+        .collect();
+        peers.sort_unstable();
+        peers.dedup();
+        peers
+    }
+
+    // The values no filled peer of (row, col) already holds
+    pub(crate) fn peer_domain(&self, row: usize, col: usize) -> Domain<BOX_ROWS, BOX_COLS> {
+        let mut domain = Domain::default();
+        for (r, c) in Self::peers(row, col) {
+            if let Some(val) = self.get(r, c) {
+                domain.mark_invalid(val);
+            }
+        }
+        domain
+    }
+
+    // The values along a row, column or box, in the order of unit_cells
+    pub fn unit(&self, unit: Unit) -> impl Iterator<Item = Option<usize>> + '_ {
+        Self::unit_cells(unit)
+            .into_iter()
+            .map(|(row, col)| self.get(row, col))
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = impl Iterator<Item = Option<usize>> + '_> + '_ {
+        (0..Self::SIZE).map(|row| self.unit(Unit::Row(row)))
+    }
+
+    pub fn cols(&self) -> impl Iterator<Item = impl Iterator<Item = Option<usize>> + '_> + '_ {
+        (0..Self::SIZE).map(|col| self.unit(Unit::Col(col)))
+    }
+
+    // Boxes left to right, top to bottom, each read left to right, top to bottom
+    pub fn boxes(&self) -> impl Iterator<Item = impl Iterator<Item = Option<usize>> + '_> + '_ {
+        (0..Self::SIZE).map(|b| self.unit(Unit::Box(b)))
+    }
+
+    // The box holding (row, col), numbered as in Unit::Box
+    pub fn box_index(row: usize, col: usize) -> usize {
+        (row / BOX_ROWS) * BOX_ROWS + col / BOX_COLS
+    }
+
+    // Every row, then every column, then every box
+    pub fn units() -> Vec<Unit> {
+        (0..Self::SIZE)
+            .map(Unit::Row)
+            .chain((0..Self::SIZE).map(Unit::Col))
+            .chain((0..Self::SIZE).map(Unit::Box))
+            .collect()
+    }
+
+    // The (row, col) of each tile in a unit
+    pub fn unit_cells(unit: Unit) -> Vec<(usize, usize)> {
+        match unit {
+            Unit::Row(row) => (0..Self::SIZE).map(|col| (row, col)).collect(),
+            Unit::Col(col) => (0..Self::SIZE).map(|row| (row, col)).collect(),
+            Unit::Box(b) => {
+                let top = (b / BOX_ROWS) * BOX_ROWS;
+                let left = (b % BOX_ROWS) * BOX_COLS;
+                (0..Self::SIZE)
+                    .map(|i| (top + i / BOX_COLS, left + i % BOX_COLS))
+                    .collect()
+            }
+        }
+    }
+
     pub fn is_valid(&self) -> bool {
         // Check rows
-        for row in 0..SUDOKU_SIZE {
-            let mut present = [false; SUDOKU_SIZE];
-            for col in 0..SUDOKU_SIZE {
+        for row in 0..Self::SIZE {
+            let mut present = [false; MAX_SIZE];
+            for col in 0..Self::SIZE {
                 let tile = self.board[row][col];
                 match tile {
                     Tile::Uncollapsed(_) => continue,
@@ -101,9 +726,9 @@ impl SudokuBoard {
         }
 
         // Check cols
-        for col in 0..SUDOKU_SIZE {
-            let mut present = [false; SUDOKU_SIZE];
-            for row in 0..SUDOKU_SIZE {
+        for col in 0..Self::SIZE {
+            let mut present = [false; MAX_SIZE];
+            for row in 0..Self::SIZE {
                 let tile = self.board[row][col];
                 match tile {
                     Tile::Uncollapsed(_) => continue,
@@ -118,13 +743,13 @@ impl SudokuBoard {
         }
 
         // Check subgrids
-        for sr in 0..SUDOKU_BASE {
-            for sc in 0..SUDOKU_BASE {
-                let mut present = [false; SUDOKU_SIZE];
-                for r in 0..SUDOKU_BASE {
-                    for c in 0..SUDOKU_BASE {
-                        let row = sr * SUDOKU_BASE + r;
-                        let col = sc * SUDOKU_BASE + c;
+        for sr in 0..BOX_COLS {
+            for sc in 0..BOX_ROWS {
+                let mut present = [false; MAX_SIZE];
+                for r in 0..BOX_ROWS {
+                    for c in 0..BOX_COLS {
+                        let row = sr * BOX_ROWS + r;
+                        let col = sc * BOX_COLS + c;
                         let tile = self.board[row][col];
                         match tile {
                             Tile::Uncollapsed(_) => continue,
@@ -144,8 +769,8 @@ impl SudokuBoard {
     }
 
     pub fn is_complete(&self) -> bool {
-        for row in 0..SUDOKU_SIZE {
-            for col in 0..SUDOKU_SIZE {
+        for row in 0..Self::SIZE {
+            for col in 0..Self::SIZE {
                 if let Tile::Uncollapsed(_) = self.board[row][col] {
                     return false;
                 }
@@ -154,9 +779,9 @@ impl SudokuBoard {
         true
     }
 
-    fn is_valid_assignment(&self, val: usize, row: usize, col: usize) -> bool {
+    pub(crate) fn is_valid_assignment(&self, val: usize, row: usize, col: usize) -> bool {
         // Row
-        for c in 0..SUDOKU_SIZE {
+        for c in 0..Self::SIZE {
             if let Tile::Collapsed(tile) = self.board[row][c] {
                 if tile == val {
                     return false;
@@ -165,7 +790,7 @@ impl SudokuBoard {
         }
 
         // Col
-        for r in 0..SUDOKU_SIZE {
+        for r in 0..Self::SIZE {
             if let Tile::Collapsed(tile) = self.board[r][col] {
                 if tile == val {
                     return false;
@@ -173,13 +798,13 @@ impl SudokuBoard {
             }
         }
 
-        // 3x3 subgrid
-        let sr = row / SUDOKU_BASE;
-        let sc = col / SUDOKU_BASE;
-        for r in 0..SUDOKU_BASE {
-            for c in 0..SUDOKU_BASE {
-                let cur_row = sr * SUDOKU_BASE + r;
-                let cur_col = sc * SUDOKU_BASE + c;
+        // BOX_ROWSxBOX_COLS subgrid
+        let sr = row / BOX_ROWS;
+        let sc = col / BOX_COLS;
+        for r in 0..BOX_ROWS {
+            for c in 0..BOX_COLS {
+                let cur_row = sr * BOX_ROWS + r;
+                let cur_col = sc * BOX_COLS + c;
                 if let Tile::Collapsed(tile) = self.board[cur_row][cur_col] {
                     if tile == val {
                         return false;
@@ -191,30 +816,32 @@ impl SudokuBoard {
         true
     }
 
-    fn get_lowest_entropy(&self) -> (usize, usize) {
-        let mut lowest_entropy = SUDOKU_SIZE+1;
-        let mut lowest_index = (usize::MAX, usize::MAX);
-        for row in 0..SUDOKU_SIZE {
-            for col in 0..SUDOKU_SIZE {
+    // The uncollapsed tile with the fewest valid values, or None if the board
+    // is complete
+    fn get_lowest_entropy(&self) -> Option<(usize, usize, Domain<BOX_ROWS, BOX_COLS>)> {
+        let mut lowest_entropy = Self::SIZE+1;
+        let mut lowest = None;
+        for row in 0..Self::SIZE {
+            for col in 0..Self::SIZE {
                 if let Tile::Uncollapsed(domain) = &self.board[row][col] {
-                    let cur_entropy = domain.get_valid().len();
+                    // A tile with no values left is a dead end, so look no further
+                    if domain.is_empty() {
+                        return Some((row, col, *domain));
+                    }
+                    let cur_entropy = domain.len();
                     if cur_entropy <= lowest_entropy {
                         lowest_entropy = cur_entropy;
-                        lowest_index = (row, col);
+                        lowest = Some((row, col, *domain));
                     }
                 }
             }
         }
-
-        if let (usize::MAX, usize::MAX) = lowest_index {
-            panic!("Board not complete but no uncollapsed tiles!");
-        }
-        lowest_index
+        lowest
     }
 
     fn propagate_uncollapsed(&mut self) {
-        for row in 0..SUDOKU_SIZE {
-            for col in 0..SUDOKU_SIZE  {
+        for row in 0..Self::SIZE {
+            for col in 0..Self::SIZE  {
                 if let Tile::Collapsed(_) = &self.board[row][col] {
                     self.propagate_collapse(row, col);
                 }
@@ -222,140 +849,330 @@ impl SudokuBoard {
         }
     }
 
-    fn propagate_collapse(&mut self, row: usize, col: usize) -> Vec<(usize, usize)> {
-        let mut modified = Vec::new();
+    // Recomputes every uncollapsed domain from the collapsed tiles alone
+    fn reset_domains(&mut self) {
+        for tile in self.board.iter_mut().flatten() {
+            if let Tile::Uncollapsed(domain) = tile {
+                *domain = Domain::default();
+            }
+        }
+        self.propagate_uncollapsed();
+    }
+
+    pub(crate) fn propagate_collapse(&mut self, row: usize, col: usize) {
+        self.remove_from_peers(row, col, None);
+    }
+
+    // Removes the value at (row, col) from its peers, recording each domain it
+    // changes on the trail if there is one
+    fn remove_from_peers(&mut self, row: usize, col: usize, mut trail: Option<&mut Trail<BOX_ROWS, BOX_COLS>>) {
         let val = match self.board[row][col] {
             Tile::Collapsed(val) => val,
             Tile::Uncollapsed(_) => panic!("Cannot propagate uncollapsed tile"),
         };
 
-        // Update row
-        for r in 0..SUDOKU_SIZE {
-            if r == row {
+        for (r, c) in Self::peer_slots(row, col) {
+            if (r, c) == (row, col) {
                 continue;
             }
-            if let Tile::Uncollapsed(domain) = &mut self.board[r][col] {
-                    if domain.mark_invalid(val) {
-                        modified.push((r, col));
+            if let Tile::Uncollapsed(domain) = &mut self.board[r][c] {
+                if domain.contains(val) {
+                    if let Some(trail) = trail.as_deref_mut() {
+                        trail.push((r, c, Tile::Uncollapsed(*domain)));
                     }
+                    domain.mark_invalid(val);
+                }
             }
         }
+    }
 
-        // Update column
-        for c in 0..SUDOKU_SIZE {
-            if c == col {
-                continue;
-            }
-            if let Tile::Uncollapsed(domain) = &mut self.board[row][c] {
-                if domain.mark_invalid(val) {
-                    modified.push((row, c));
-                }
-            }
+    // The tiles of the row, column and box of (row, col). Tiles in more than
+    // one unit and (row, col) itself appear more than once.
+    fn peer_slots(row: usize, col: usize) -> impl Iterator<Item = (usize, usize)> {
+        let box_row = row / BOX_ROWS * BOX_ROWS;
+        let box_col = col / BOX_COLS * BOX_COLS;
+        (0..Self::SIZE)
+            .map(move |r| (r, col))
+            .chain((0..Self::SIZE).map(move |c| (row, c)))
+            .chain((0..Self::SIZE).map(move |i| (box_row + i / BOX_COLS, box_col + i % BOX_COLS)))
+    }
+
+    // Collapses (row, col) to val and propagates it, recording every tile it
+    // changes on the trail and reporting them to observer
+    pub(crate) fn assign(
+        &mut self,
+        row: usize,
+        col: usize,
+        val: usize,
+        trail: &mut Trail<BOX_ROWS, BOX_COLS>,
+        observer: &mut dyn SolverObserver<BOX_ROWS, BOX_COLS>,
+    ) {
+        let mark = trail.len();
+        trail.push((row, col, self.board[row][col]));
+        self.board[row][col] = Tile::Collapsed(val);
+        observer.on_assign(self, row, col, val);
+        self.remove_from_peers(row, col, Some(trail));
+        for &(r, c, _) in &trail[mark + 1..] {
+            observer.on_eliminate(self, r, c, val);
         }
-        
-        // Update subgrid
-        let subgrid_row = row / SUDOKU_BASE;
-        let subgrid_col = col / SUDOKU_BASE;
-        for r in 0..SUDOKU_BASE {
-            for c in 0..SUDOKU_BASE {
-                let s_row = subgrid_row*SUDOKU_BASE + r;
-                let s_col = subgrid_col*SUDOKU_BASE + c;
-                if s_row == row || s_col == col {
-                    continue;
-                }
-                if let Tile::Uncollapsed(domain) = &mut self.board[s_row][s_col] {
-                    if domain.mark_invalid(val) {
-                        modified.push((s_row, s_col));
-                    }
-                }
-            }
+    }
+
+    // Puts back every tile changed since the trail was mark entries long
+    fn undo(&mut self, trail: &mut Trail<BOX_ROWS, BOX_COLS>, mark: usize) {
+        for (row, col, tile) in trail.drain(mark..).rev() {
+            self.board[row][col] = tile;
         }
+    }
 
-        modified
+    // Returns stats with outcome Unsolvable if the board has no solution, or
+    // Err if it already breaks the rules
+    pub fn solve_csp(&mut self) -> Result<SolveStats, SudokuError> {
+        self.solve_csp_with(&SolverOptions::default(), &mut MinimumRemainingValues, &mut ())
+    }
 
+    // solve_csp with the given settings, branching where strategy says and
+    // reporting each change to observer. If the budget runs out the outcome is
+    // Aborted and the board is left as it was.
+    pub fn solve_csp_with(
+        &mut self,
+        options: &SolverOptions,
+        strategy: &mut impl SearchStrategy<BOX_ROWS, BOX_COLS>,
+        observer: &mut impl SolverObserver<BOX_ROWS, BOX_COLS>,
+    ) -> Result<SolveStats, SudokuError> {
+        if !self.is_valid() {
+            return Err(SudokuError::InvalidBoard);
+        }
+        let start = Instant::now();
+        let mut search = CspSearch {
+            options,
+            strategy,
+            observer,
+            trail: Trail::new(),
+            meter: Meter::new(&options.budget),
+            stats: SolveStats::default(),
+        };
+        search.stats.outcome = match self.search_csp(&mut search) {
+            true => SolveOutcome::Solved,
+            false if search.meter.aborted => SolveOutcome::Aborted,
+            false => SolveOutcome::Unsolvable,
+        };
+        if !search.stats.is_solved() {
+            self.undo(&mut search.trail, 0);
+        }
+        search.stats.nodes = search.meter.nodes;
+        search.stats.elapsed = start.elapsed();
+        Ok(search.stats)
     }
 
-    fn restore_domain(&mut self, val: usize, collapsed: Vec<(usize, usize)>) {
-        for (row, col) in collapsed {
-            if let Tile::Uncollapsed(domain) = &mut self.board[row][col] {
-                domain.mark_valid(val);
-            } else {
-                self.board[row][col] = Tile::Uncollapsed(Domain::from_value(val));
+    // Depth-first search that keeps the tiles it is branching on in a Vec
+    // rather than recursing, so its depth is bounded only by the heap
+    fn search_csp<S: SearchStrategy<BOX_ROWS, BOX_COLS>>(&mut self, search: &mut CspSearch<S, BOX_ROWS, BOX_COLS>) -> bool {
+        let mut branches: Vec<Branch> = Vec::new();
+        'nodes: loop {
+            if self.expand_csp_node(search, &mut branches) {
+                return true;
             }
-        }
 
+            // Take back the value that just failed, and try the next one of
+            // the deepest branch that has any left
+            while let Some(branch) = branches.last_mut() {
+                let (row, col) = (branch.row, branch.col);
+                if let Some((val, mark)) = branch.tried.take() {
+                    self.undo(&mut search.trail, mark);
+                    if !search.meter.aborted {
+                        search.stats.backtracks += 1;
+                        search.observer.on_backtrack(self, row, col, val);
+                    }
+                }
+                while !search.meter.aborted && branch.next < branch.count {
+                    let val = branch.values[branch.next];
+                    branch.next += 1;
+                    if self.is_valid_assignment(val, row, col) {
+                        if branch.count > 1 {
+                            search.stats.guesses += 1;
+                        }
+                        let mark = search.trail.len();
+                        self.assign(row, col, val, &mut search.trail, search.observer);
+                        // Everything but the tile itself was changed by propagation
+                        search.stats.propagations += (search.trail.len() - mark - 1) as u64;
+                        branch.tried = Some((val, mark));
+                        continue 'nodes;
+                    }
+                }
+                branches.pop();
+            }
+            return false;
+        }
     }
 
-    pub fn solve_csp(&mut self, debug_delay_ms: u64, steps: &mut u32) -> bool {
-        if debug_delay_ms > 0 {
-            std::thread::sleep(std::time::Duration::from_millis(debug_delay_ms));
-            self.print(true);
+    // Propagates at the node below the last of branches, then pushes the
+    // tile to branch on there with its values in the order to try them.
+    // Returns true if the board is solved, and pushes nothing if the node is
+    // a dead end.
+    fn expand_csp_node<S: SearchStrategy<BOX_ROWS, BOX_COLS>>(
+        &mut self,
+        search: &mut CspSearch<S, BOX_ROWS, BOX_COLS>,
+        branches: &mut Vec<Branch>,
+    ) -> bool {
+        if !search.meter.tick() {
+            return false;
         }
-        *steps += 1;
-        
-        if self.is_complete() {
-            return true;
+        search.stats.max_depth = search.stats.max_depth.max(branches.len());
+
+        // The branch above undoes whatever this changes if the node fails
+        let mark = search.trail.len();
+        let consistent = self.propagate(search.options.propagation, &mut search.trail, search.observer);
+        search.stats.propagations += (search.trail.len() - mark) as u64;
+        if !consistent {
+            search.stats.wipeouts += 1;
+            return false;
         }
-        
-        let (row, col) = self.get_lowest_entropy();
-        
-        let valid = match self.board[row][col] {
-            Tile::Collapsed(_) => panic!("get_lowest_entropy() should never return a collapsed tile"),
-            Tile::Uncollapsed(domain) => domain.get_valid(),
+
+        let (row, col) = match search.strategy.select_tile(self) {
+            Some(tile) => tile,
+            None => {
+                search.observer.on_solution(self);
+                return true;
+            }
         };
 
-        for val in valid {
-            if self.is_valid_assignment(val, row, col) {
-                let saved_domain = if let Tile::Uncollapsed(domain) = &self.board[row][col] {
-                    domain.clone()
-                } else {
-                    panic!("tile was uncollapsed, but now it's not: ({}, {})", row, col);
-                };
-                
-                self.board[row][col] = Tile::Collapsed(val);
-                let collapsed_states = self.propagate_collapse(row, col);
-                if self.solve_csp(debug_delay_ms, steps) {
-                    return true;
-                }
-                self.restore_domain(val, collapsed_states);
-                self.board[row][col] = Tile::Uncollapsed(saved_domain);
+        let mut branch = Branch {
+            row,
+            col,
+            values: [0; MAX_SIZE],
+            count: 0,
+            next: 0,
+            tried: None,
+        };
+        if let Tile::Uncollapsed(domain) = self.board[row][col] {
+            for val in domain {
+                branch.values[branch.count] = val;
+                branch.count += 1;
             }
         }
-
+        if branch.count == 0 {
+            search.stats.wipeouts += 1;
+            return false;
+        }
+        search.strategy.order_values(self, row, col, &mut branch.values[..branch.count]);
+        branches.push(branch);
         false
     }
 
-    pub fn solve_brute_force(&mut self, debug_delay_ms: u64, steps: &mut u32) -> bool {
-        if debug_delay_ms > 0 {
-            std::thread::sleep(std::time::Duration::from_millis(debug_delay_ms));
-            self.print(true);
-        }
-        *steps += 1;
-        if self.is_complete() {
-            return true;
-        }
-        for row in 0..SUDOKU_SIZE {
-            for col in 0..SUDOKU_SIZE {
-                if let Tile::Collapsed(_) = self.board[row][col] {
-                    continue;
+    // Same search as solve_csp, but keeps going after the first solution and
+    // returns how many were found, stopping early once limit is reached or
+    // meter runs out
+    fn count_solutions_csp(
+        &mut self,
+        limit: usize,
+        trail: &mut Trail<BOX_ROWS, BOX_COLS>,
+        meter: &mut Meter,
+    ) -> usize {
+        // (row, col, values left to try, value being searched below and the
+        // trail length from before it) of each tile being branched on
+        let mut branches: Vec<(usize, usize, DomainIter, Option<usize>)> = Vec::new();
+        let mut count = 0;
+        'nodes: loop {
+            if meter.tick() {
+                match self.get_lowest_entropy() {
+                    Some((row, col, domain)) => branches.push((row, col, domain.iter(), None)),
+                    None => count += 1,
                 }
+            }
 
-                for s in 1..=SUDOKU_SIZE {
-                    //self.board[row][col] = s;
-                    if self.is_valid_assignment(s, row, col) {
-                        //if self.is_valid() {
-                        self.board[row][col] = Tile::Collapsed(s);
-                        if self.solve_brute_force(debug_delay_ms, steps) {
-                            return true;
+            while let Some((row, col, values, tried)) = branches.last_mut() {
+                if let Some(mark) = tried.take() {
+                    self.undo(trail, mark);
+                }
+                if count < limit && !meter.aborted {
+                    for val in values.by_ref() {
+                        if self.is_valid_assignment(val, *row, *col) {
+                            *tried = Some(trail.len());
+                            self.assign(*row, *col, val, trail, &mut ());
+                            continue 'nodes;
                         }
                     }
-                    self.board[row][col] = Tile::default();
                 }
-                return false;
+                branches.pop();
             }
+            return count;
         }
+    }
 
-        false
+    // Returns stats with outcome Unsolvable if the board has no solution, or
+    // Err if it already breaks the rules. Nothing is propagated, so
+    // propagations and wipeouts stay 0.
+    pub fn solve_brute_force(&mut self) -> Result<SolveStats, SudokuError> {
+        self.solve_brute_force_with(&Budget::default(), &mut ())
+    }
+
+    // solve_brute_force, stopping with outcome Aborted if budget runs out and
+    // reporting each change to observer
+    pub fn solve_brute_force_with(
+        &mut self,
+        budget: &Budget,
+        observer: &mut impl SolverObserver<BOX_ROWS, BOX_COLS>,
+    ) -> Result<SolveStats, SudokuError> {
+        if !self.is_valid() {
+            return Err(SudokuError::InvalidBoard);
+        }
+        let start = Instant::now();
+        let mut stats = SolveStats::default();
+        let mut meter = Meter::new(budget);
+        stats.outcome = match self.search_brute_force(observer, &mut stats, &mut meter) {
+            true => SolveOutcome::Solved,
+            false if meter.aborted => SolveOutcome::Aborted,
+            false => SolveOutcome::Unsolvable,
+        };
+        stats.nodes = meter.nodes;
+        stats.elapsed = start.elapsed();
+        Ok(stats)
+    }
+
+    // Fills the first empty tile with each value that fits in turn, keeping
+    // the tiles being filled in a Vec like search_csp does
+    fn search_brute_force(
+        &mut self,
+        observer: &mut impl SolverObserver<BOX_ROWS, BOX_COLS>,
+        stats: &mut SolveStats,
+        meter: &mut Meter,
+    ) -> bool {
+        // (row, col, value placed there, tile it replaced) of each tile being
+        // filled, with 0 before the first value
+        let mut branches: Vec<(usize, usize, usize, Tile<BOX_ROWS, BOX_COLS>)> = Vec::new();
+        'nodes: loop {
+            if meter.tick() {
+                stats.max_depth = stats.max_depth.max(branches.len());
+                if self.is_complete() {
+                    observer.on_solution(self);
+                    return true;
+                }
+                if let Some((row, col, _)) = self.empty_tiles().next() {
+                    branches.push((row, col, 0, self.board[row][col]));
+                }
+            }
+
+            while let Some(&mut (row, col, ref mut val, prior)) = branches.last_mut() {
+                if *val != 0 {
+                    self.board[row][col] = prior;
+                    if !meter.aborted {
+                        stats.backtracks += 1;
+                        observer.on_backtrack(self, row, col, *val);
+                    }
+                }
+                while !meter.aborted && *val < Self::SIZE {
+                    *val += 1;
+                    if self.is_valid_assignment(*val, row, col) {
+                        stats.guesses += 1;
+                        self.board[row][col] = Tile::Collapsed(*val);
+                        observer.on_assign(self, row, col, *val);
+                        continue 'nodes;
+                    }
+                }
+                branches.pop();
+            }
+            return false;
+        }
     }
 
     // Adapted from https://stackoverflow.com/questions/45471152/how-to-create-a-sudoku-puzzle-in-python
@@ -365,35 +1182,40 @@ impl SudokuBoard {
         }
         let board = &self.board;
 
-        fn expand_line(line: &str) -> String {
+        // Each box spans box_cols tiles, and there are num_boxes boxes per row
+        fn expand_line(line: &str, box_cols: usize, num_boxes: usize) -> String {
             let chars: Vec<char> = line.chars().collect();
-            let output_vec: String = chars[1..5].repeat(SUDOKU_BASE - 1).iter().collect();
+            let output_vec: String = chars[1..5].repeat(box_cols - 1).iter().collect();
             let sep: String = chars[5..9].iter().collect();
-            let out = vec![output_vec.as_str()]
-                .repeat(SUDOKU_BASE)
+            let out = [output_vec.as_str()]
+                .repeat(num_boxes)
                 .join(sep.as_str());
             [chars[0].to_string(), out, chars[9..13].iter().collect()].join("")
         }
 
-        let line0 = expand_line("╔═══╤═══╦═══╗");
-        let line1 = expand_line("║ . │ . ║ . ║");
-        let line2 = expand_line("╟───┼───╫───╢");
-        let line3 = expand_line("╠═══╪═══╬═══╣");
-        let line4 = expand_line("╚═══╧═══╩═══╝");
+        let line0 = expand_line("╔═══╤═══╦═══╗", BOX_COLS, BOX_ROWS);
+        let line1 = expand_line("║ . │ . ║ . ║", BOX_COLS, BOX_ROWS);
+        let line2 = expand_line("╟───┼───╫───╢", BOX_COLS, BOX_ROWS);
+        let line3 = expand_line("╠═══╪═══╬═══╣", BOX_COLS, BOX_ROWS);
+        let line4 = expand_line("╚═══╧═══╩═══╝", BOX_COLS, BOX_ROWS);
 
-        let mut nums: [[char; SUDOKU_SIZE]; SUDOKU_SIZE] = [[' '; SUDOKU_SIZE]; SUDOKU_SIZE];
-        for (row_num, row) in board.iter().enumerate() {
-            for (col_num, tile) in row.iter().enumerate() {
-                nums[row_num][col_num] = match tile {
-                    Tile::Uncollapsed(_) => ' ',
-                    Tile::Collapsed(v) => char::from_digit(*v as u32, 10).unwrap(),
-                }
-            }
-        }
+        let symbol: Vec<char> = " 1234567890ABCDEFGHIJKLMNOPQRSTUVWXYZ".chars().collect();
+
+        let nums: Vec<Vec<char>> = board
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|tile| match tile {
+                        Tile::Uncollapsed(_) => ' ',
+                        Tile::Collapsed(v) => symbol[*v],
+                    })
+                    .collect()
+            })
+            .collect();
 
         println!("{}", line0);
-        for r in 0..SUDOKU_SIZE {
-            for (c, v) in nums[r].iter().zip(line1.split(".")) {
+        for r in 0..Self::SIZE {
+            for (c, v) in nums[r].iter().zip(line1.split('.')) {
                 print!("{}{}", v, c);
             }
 
@@ -401,15 +1223,73 @@ impl SudokuBoard {
             println!(
                 "{}",
                 [&line2, &line3, &line4]
-                    [((r + 1) % SUDOKU_BASE == 0) as usize + (r == SUDOKU_SIZE - 1) as usize]
+                    [((r + 1) % BOX_ROWS == 0) as usize + (r == Self::SIZE - 1) as usize]
             )
         }
     }
 }
 
+// Iterator returned by SudokuBoard::solutions. A depth-first search that
+// branches on a tile with the fewest candidates and keeps the unexplored
+// branches on a stack between calls.
+pub struct Solutions<const BOX_ROWS: usize, const BOX_COLS: usize> {
+    stack: Vec<SudokuBoard<BOX_ROWS, BOX_COLS>>,
+}
+
+impl<const BOX_ROWS: usize, const BOX_COLS: usize> Iterator for Solutions<BOX_ROWS, BOX_COLS> {
+    type Item = SudokuBoard<BOX_ROWS, BOX_COLS>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(board) = self.stack.pop() {
+            let (row, col, domain) = match board.get_lowest_entropy() {
+                Some(tile) => tile,
+                None => return Some(board),
+            };
+
+            // Pushed in reverse so the smallest value is explored first
+            for val in domain.iter().rev() {
+                if board.is_valid_assignment(val, row, col) {
+                    let mut next = board.clone();
+                    next.board[row][col] = Tile::Collapsed(val);
+                    next.propagate_collapse(row, col);
+                    self.stack.push(next);
+                }
+            }
+        }
+        None
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::board::{SudokuBoard, SUDOKU_SIZE};
+    use crate::board::{SudokuBoard, Unit, SUDOKU_SIZE};
+    use crate::error::SudokuError;
+    use crate::solver::{Budget, Meter, SolverOptions};
+    use crate::strategy::MinimumRemainingValues;
+    use crate::observer::SolverObserver;
+    use crate::tile::{Domain, Tile};
+
+    type Board = SudokuBoard<3>;
+
+    // Fails if any domain differs from what a fresh propagation computes
+    fn assert_domains_fresh<const BOX_ROWS: usize, const BOX_COLS: usize>(board: &SudokuBoard<BOX_ROWS, BOX_COLS>) {
+        let mut fresh = board.clone();
+        fresh.reset_domains();
+        for (row, col) in (0..fresh.board.len()).flat_map(|row| (0..fresh.board.len()).map(move |col| (row, col))) {
+            if let (Tile::Uncollapsed(domain), Tile::Uncollapsed(expected)) = (board.board[row][col], fresh.board[row][col]) {
+                assert_eq!(domain, expected, "domain of ({}, {}) after backtracking", row, col);
+            }
+        }
+    }
+
+    // Checks the domains after every backtrack of a forward checking search
+    struct FreshDomains;
+
+    impl<const BOX_ROWS: usize, const BOX_COLS: usize> SolverObserver<BOX_ROWS, BOX_COLS> for FreshDomains {
+        fn on_backtrack(&mut self, board: &SudokuBoard<BOX_ROWS, BOX_COLS>, _row: usize, _col: usize, _val: usize) {
+            assert_domains_fresh(board);
+        }
+    }
 
     const ARRAY_SIZE: usize = SUDOKU_SIZE * SUDOKU_SIZE;
 
@@ -437,7 +1317,7 @@ mod tests {
 
     #[test]
     fn test_load_sparse_array() {
-        let board = SudokuBoard::from_array(_TEST_SPARSE_ARRAY);
+        let board = Board::from_array(&_TEST_SPARSE_ARRAY).unwrap();
         assert!(board.is_valid());
         assert!(!board.is_complete());
     }
@@ -447,7 +1327,7 @@ mod tests {
         let mut test_array = [0; ARRAY_SIZE];
         test_array[0] = 9;
         test_array[1] = 9;
-        let board = SudokuBoard::from_array(test_array);
+        let board = Board::from_array(&test_array).unwrap();
         assert!(!board.is_valid());
         assert!(!board.is_complete());
     }
@@ -457,7 +1337,7 @@ mod tests {
         let mut test_array = [0; ARRAY_SIZE];
         test_array[0] = 9;
         test_array[9] = 9;
-        let board = SudokuBoard::from_array(test_array);
+        let board = Board::from_array(&test_array).unwrap();
         assert!(!board.is_valid());
         assert!(!board.is_complete());
     }
@@ -467,22 +1347,284 @@ mod tests {
         let mut test_array = [0; ARRAY_SIZE];
         test_array[1] = 5;
         test_array[9] = 5;
-        let board = SudokuBoard::from_array(test_array);
+        let board = Board::from_array(&test_array).unwrap();
         assert!(!board.is_valid());
         assert!(!board.is_complete());
     }
 
     #[test]
     fn test_invalid_subgrid_9s() {
-        let board = SudokuBoard::from_array(_TEST_DUPLICATE_9S_INVALID);
+        let board = Board::from_array(&_TEST_DUPLICATE_9S_INVALID).unwrap();
         assert!(!board.is_valid());
         assert!(!board.is_complete());
     }
 
     #[test]
     fn test_load_full_array() {
-        let board = SudokuBoard::from_array(_TEST_SOLUTION_ARRAY);
+        let board = Board::from_array(&_TEST_SOLUTION_ARRAY).unwrap();
+        assert!(board.is_valid());
+        assert!(board.is_complete());
+    }
+
+    #[test]
+    fn test_load_full_array_base_2() {
+        let board = SudokuBoard::<2>::from_array(&[
+            1, 2, 3, 4,
+            3, 4, 1, 2,
+            2, 1, 4, 3,
+            4, 3, 2, 1,
+        ]).unwrap();
+        assert!(board.is_valid());
+        assert!(board.is_complete());
+        assert_eq!(board.into_array()[4..8], [3, 4, 1, 2]);
+    }
+
+    #[test]
+    fn test_invalid_subgrid_base_4() {
+        let mut test_array = [0; 16 * 16];
+        test_array[3] = 16;
+        test_array[16] = 16;
+        let board = SudokuBoard::<4>::from_array(&test_array).unwrap();
+        assert!(!board.is_valid());
+        assert!(!board.is_complete());
+    }
+
+    #[test]
+    fn test_load_full_array_2x3() {
+        let board = SudokuBoard::<2, 3>::from_array(&[
+            1, 2, 3, 4, 5, 6,
+            4, 5, 6, 1, 2, 3,
+            2, 3, 1, 5, 6, 4,
+            5, 6, 4, 2, 3, 1,
+            3, 1, 2, 6, 4, 5,
+            6, 4, 5, 3, 1, 2,
+        ]).unwrap();
         assert!(board.is_valid());
         assert!(board.is_complete());
     }
+
+    #[test]
+    fn test_invalid_subgrid_2x3() {
+        // (0, 2) and (1, 0) share a 2x3 box, but not a 3x2 one
+        let mut test_array = [0; 36];
+        test_array[2] = 4;
+        test_array[6] = 4;
+        assert!(!SudokuBoard::<2, 3>::from_array(&test_array).unwrap().is_valid());
+        assert!(SudokuBoard::<3, 2>::from_array(&test_array).unwrap().is_valid());
+    }
+
+    #[test]
+    fn test_unique_solution() {
+        assert!(Board::from_array(&_TEST_SINGLE_SOLN_ARRAY).unwrap().has_unique_solution());
+        assert!(Board::from_array(&_TEST_SOLUTION_ARRAY).unwrap().has_unique_solution());
+        assert!(!Board::from_array(&[0; ARRAY_SIZE]).unwrap().has_unique_solution());
+        assert!(!Board::from_array(&_TEST_DUPLICATE_9S_INVALID).unwrap().has_unique_solution());
+    }
+
+    #[test]
+    fn test_count_solutions_stops_at_limit() {
+        let mut board = Board::from_array(&[0; ARRAY_SIZE]).unwrap();
+        assert_eq!(board.count_solutions_csp(5, &mut Vec::new(), &mut Meter::new(&Budget::default())), 5);
+    }
+
+    #[test]
+    fn test_backtracking_restores_fresh_domains() {
+        // Counting undoes every branch it took, back to the board it started on
+        let mut empty = SudokuBoard::<2>::from_array(&[0; 16]).unwrap();
+        for limit in [5, usize::MAX] {
+            empty.count_solutions_csp(limit, &mut Vec::new(), &mut Meter::new(&Budget::default()));
+            assert_eq!(empty.into_array(), [0; 16]);
+            assert_domains_fresh(&empty);
+        }
+
+        // AI Escargot, which needs plenty of backtracking
+        let escargot: Vec<usize> = "1....7.9..3..2...8..96..5....53..9...1..8...26....4...3......1..4......7..7...3.."
+            .chars()
+            .map(|c| c.to_digit(10).unwrap_or(0) as usize)
+            .collect();
+        let mut board = Board::from_array(&escargot).unwrap();
+        let stats = board
+            .solve_csp_with(&SolverOptions::default(), &mut MinimumRemainingValues, &mut FreshDomains)
+            .unwrap();
+        assert!(stats.is_solved() && stats.backtracks > 0);
+        assert!(board.is_complete());
+
+        // Undoing an assignment empties the trail and puts the tile back
+        let mut trail = Vec::new();
+        let mut board = Board::from_array(&_TEST_SINGLE_SOLN_ARRAY).unwrap();
+        board.assign(0, 2, 5, &mut trail, &mut ());
+        assert_eq!(board.get(0, 2), Some(5));
+        board.undo(&mut trail, 0);
+        assert!(trail.is_empty());
+        assert_eq!(board.get(0, 2), None);
+    }
+
+    #[test]
+    fn test_dlx_ignores_cached_domains() {
+        let puzzle = Board::from_array(&_TEST_SINGLE_SOLN_ARRAY).unwrap();
+        let solution = puzzle.solutions().next().unwrap().into_array();
+
+        // Every empty tile claims every value, as after a missed propagation,
+        // except the first, which claims only a wrong one
+        let mut stale = puzzle.clone();
+        for row in stale.board.iter_mut() {
+            for tile in row.iter_mut() {
+                if let Tile::Uncollapsed(_) = tile {
+                    *tile = Tile::default();
+                }
+            }
+        }
+        let first = _TEST_SINGLE_SOLN_ARRAY.iter().position(|&val| val == 0).unwrap();
+        let wrong = Domain::from_value(solution[first] % SUDOKU_SIZE + 1).unwrap();
+        stale.board[first / SUDOKU_SIZE][first % SUDOKU_SIZE] = Tile::Uncollapsed(wrong);
+
+        assert_eq!(stale.count_solutions_dlx(usize::MAX), 1);
+        assert!(stale.solve_dlx().unwrap().is_solved());
+        assert_eq!(stale.into_array(), solution);
+    }
+
+    #[test]
+    fn test_count_solutions_invalid() {
+        assert_eq!(Board::from_array(&_TEST_DUPLICATE_9S_INVALID).unwrap().count_solutions(10), 0);
+        assert_eq!(Board::from_array(&_TEST_DUPLICATE_9S_INVALID).unwrap().solutions().count(), 0);
+    }
+
+    #[test]
+    fn test_from_array_wrong_length() {
+        assert_eq!(
+            Board::from_array(&[0; 80]).err(),
+            Some(SudokuError::WrongLength { expected: 81, found: 80 })
+        );
+    }
+
+    #[test]
+    fn test_from_array_value_too_large() {
+        let mut test_array = [0; ARRAY_SIZE];
+        test_array[10] = 10;
+        assert_eq!(
+            Board::from_array(&test_array).err(),
+            Some(SudokuError::InvalidTile { row: 1, col: 1, value: 10 })
+        );
+        assert!(SudokuBoard::<2, 5>::from_array(&[10; 100]).is_ok());
+    }
+
+    #[test]
+    fn test_generate_too_many_filled() {
+        assert_eq!(
+            Board::generate(82).err(),
+            Some(SudokuError::TooManyFilled { requested: 82, max: 81 })
+        );
+        assert!(Board::generate(81).unwrap().is_complete());
+    }
+
+    #[test]
+    fn test_solve_invalid_board() {
+        let mut board = Board::from_array(&_TEST_DUPLICATE_9S_INVALID).unwrap();
+        assert_eq!(board.solve_csp(), Err(SudokuError::InvalidBoard));
+        assert_eq!(board.solve_brute_force(), Err(SudokuError::InvalidBoard));
+    }
+
+    #[test]
+    fn test_solve_unsolvable_board() {
+        // Valid so far, but nothing can go in the top left tile
+        let mut test_array = [0; ARRAY_SIZE];
+        test_array[1..9].copy_from_slice(&[1, 2, 3, 4, 5, 6, 7, 8]);
+        test_array[9 * 4] = 9;
+        let mut board = Board::from_array(&test_array).unwrap();
+        assert!(!board.solve_csp().unwrap().is_solved());
+        assert!(!board.solve_brute_force().unwrap().is_solved());
+    }
+
+    // Candidates everywhere match a board rebuilt from scratch
+    fn assert_candidates_fresh(board: &Board) {
+        let fresh = Board::from_array(&board.into_array()).unwrap();
+        for row in 0..9 {
+            for col in 0..9 {
+                assert_eq!(board.candidates(row, col), fresh.candidates(row, col), "({}, {})", row, col);
+            }
+        }
+    }
+
+    #[test]
+    fn test_get_set_clear() {
+        let mut board = Board::from_array(&_TEST_SPARSE_ARRAY).unwrap();
+        assert_eq!(board.get(0, 7), Some(9));
+        assert_eq!(board.get(0, 0), None);
+
+        board.set(0, 0, 4).unwrap();
+        assert_eq!(board.get(0, 0), Some(4));
+        assert!(!board.candidates(0, 1).contains(&4));
+        assert!(!board.candidates(5, 0).contains(&4));
+        assert!(!board.candidates(2, 2).contains(&4));
+        assert_candidates_fresh(&board);
+
+        board.set(0, 0, 5).unwrap();
+        assert_eq!(board.get(0, 0), Some(5));
+        assert!(board.candidates(0, 2).contains(&4));
+        assert_candidates_fresh(&board);
+
+        board.clear(0, 0).unwrap();
+        assert_eq!(board.get(0, 0), None);
+        assert_eq!(board.into_array(), _TEST_SPARSE_ARRAY);
+        assert_candidates_fresh(&board);
+    }
+
+    #[test]
+    fn test_clear_keeps_value_blocked_by_other_peer() {
+        let mut board = Board::from_array(&_TEST_SPARSE_ARRAY).unwrap();
+        // (1, 0) holds 9, and (0, 7) keeps 9 out of row 0 anyway
+        board.clear(1, 0).unwrap();
+        assert!(!board.candidates(0, 0).contains(&9));
+        assert!(board.candidates(2, 0).contains(&9));
+        assert_candidates_fresh(&board);
+    }
+
+    #[test]
+    fn test_set_clear_sequence_matches_fresh_board() {
+        let mut board = Board::from_array(&_TEST_SINGLE_SOLN_ARRAY).unwrap();
+        for i in 0..200 {
+            let (row, col) = ((i * 7) % 9, (i * 5 + i / 9) % 9);
+            match i % 3 {
+                0 => board.clear(row, col).unwrap(),
+                _ => board.set(row, col, i % 9 + 1).unwrap(),
+            }
+            assert_candidates_fresh(&board);
+        }
+    }
+
+    #[test]
+    fn test_set_clear_errors() {
+        let mut board = Board::from_array(&[0; ARRAY_SIZE]).unwrap();
+        assert_eq!(board.set(0, 0, 0), Err(SudokuError::ValueOutOfRange { value: 0, size: 9 }));
+        assert_eq!(board.set(0, 0, 10), Err(SudokuError::ValueOutOfRange { value: 10, size: 9 }));
+        assert_eq!(board.set(9, 0, 1), Err(SudokuError::OutOfBounds { row: 9, col: 0 }));
+        assert_eq!(board.clear(0, 9), Err(SudokuError::OutOfBounds { row: 0, col: 9 }));
+        assert_eq!(board.into_array(), vec![0; ARRAY_SIZE]);
+    }
+
+    #[test]
+    fn test_unit_iterators() {
+        let board = SudokuBoard::<2, 3>::from_array(&[
+            1, 2, 3, 4, 5, 6,
+            4, 5, 6, 1, 2, 3,
+            2, 3, 1, 5, 6, 4,
+            5, 6, 4, 2, 3, 1,
+            3, 1, 2, 6, 4, 5,
+            6, 4, 5, 3, 1, 0,
+        ]).unwrap();
+        let rows: Vec<Vec<Option<usize>>> = board.rows().map(|row| row.collect()).collect();
+        assert_eq!(rows[1], [4, 5, 6, 1, 2, 3].map(Some));
+        assert_eq!(rows[5][5], None);
+
+        let cols: Vec<Vec<Option<usize>>> = board.cols().map(|col| col.collect()).collect();
+        assert_eq!(cols[0], [1, 4, 2, 5, 3, 6].map(Some));
+
+        let boxes: Vec<Vec<Option<usize>>> = board.boxes().map(|b| b.collect()).collect();
+        assert_eq!(boxes.len(), 6);
+        assert_eq!(boxes[1], [4, 5, 6, 1, 2, 3].map(Some));
+        assert_eq!(boxes[2], [2, 3, 1, 5, 6, 4].map(Some));
+        assert_eq!(board.unit(Unit::Box(5)).last().unwrap(), None);
+        assert_eq!(SudokuBoard::<2, 3>::box_index(5, 5), 5);
+        assert_eq!(SudokuBoard::<2, 3>::box_index(2, 3), 3);
+    }
 }
@@ -0,0 +1,443 @@
+use crate::board::SudokuBoard;
+use std::time::Instant;
+
+use crate::error::SudokuError;
+use crate::solver::{Budget, Meter, SolveOutcome, SolveStats};
+use crate::tile::{Domain, Tile};
+
+// Knuth's Dancing Links over a sparse 0/1 matrix. Node 0 is the root, nodes
+// 1..=columns are the column headers and every other node is a 1 in some row.
+// The search is iterative, so it can stop after each solution and resume.
+pub(crate) struct Dlx {
+    left: Vec<usize>,
+    right: Vec<usize>,
+    up: Vec<usize>,
+    down: Vec<usize>,
+    col: Vec<usize>,
+    // Caller's id for the row each node belongs to
+    row: Vec<usize>,
+    // Number of nodes left in each column
+    size: Vec<usize>,
+    // Row node picked at each level of the search
+    chosen: Vec<usize>,
+    // How many rows at the start of chosen were fixed before the search and
+    // are never taken back
+    fixed: usize,
+    // True once a solution has been returned, so the next call backtracks
+    resume: bool,
+    done: bool,
+    // Everything but outcome, nodes and elapsed, counted over every call so
+    // far. The meter passed to next_solution counts the nodes.
+    pub(crate) stats: SolveStats,
+}
+
+impl Dlx {
+    pub(crate) fn new(columns: usize) -> Self {
+        let headers = columns + 1;
+        let mut dlx = Self {
+            left: (0..headers).map(|i| (i + headers - 1) % headers).collect(),
+            right: (0..headers).map(|i| (i + 1) % headers).collect(),
+            up: (0..headers).collect(),
+            down: (0..headers).collect(),
+            col: (0..headers).collect(),
+            row: vec![usize::MAX; headers],
+            size: vec![0; headers],
+            chosen: Vec::new(),
+            fixed: 0,
+            resume: false,
+            done: false,
+            stats: SolveStats::default(),
+        };
+        dlx.size[0] = usize::MAX;
+        dlx
+    }
+
+    // Adds a row with a 1 in each of the given columns, numbered from 0, and
+    // returns its first node
+    pub(crate) fn add_row(&mut self, id: usize, columns: &[usize]) -> usize {
+        let first = self.left.len();
+        for (i, &c) in columns.iter().enumerate() {
+            let c = c + 1;
+            let node = first + i;
+            let last = match i {
+                0 => node,
+                _ => node - 1,
+            };
+            self.left.push(last);
+            self.right.push(first);
+            self.right[last] = node;
+            self.left[first] = node;
+
+            self.up.push(self.up[c]);
+            self.down.push(c);
+            let above = self.up[c];
+            self.down[above] = node;
+            self.up[c] = node;
+
+            self.col.push(c);
+            self.row.push(id);
+            self.size[c] += 1;
+        }
+        first
+    }
+
+    // Puts the row starting at node r in every cover, before the search
+    // starts. Its columns must not overlap those of any row fixed before.
+    pub(crate) fn fix_row(&mut self, r: usize) {
+        self.cover(self.col[r]);
+        self.select(r);
+        self.fixed += 1;
+    }
+
+    fn cover(&mut self, c: usize) {
+        self.right[self.left[c]] = self.right[c];
+        self.left[self.right[c]] = self.left[c];
+        let mut i = self.down[c];
+        while i != c {
+            let mut j = self.right[i];
+            while j != i {
+                self.down[self.up[j]] = self.down[j];
+                self.up[self.down[j]] = self.up[j];
+                self.size[self.col[j]] -= 1;
+                j = self.right[j];
+            }
+            i = self.down[i];
+        }
+    }
+
+    fn uncover(&mut self, c: usize) {
+        let mut i = self.up[c];
+        while i != c {
+            let mut j = self.left[i];
+            while j != i {
+                self.size[self.col[j]] += 1;
+                self.down[self.up[j]] = j;
+                self.up[self.down[j]] = j;
+                j = self.left[j];
+            }
+            i = self.up[i];
+        }
+        self.right[self.left[c]] = c;
+        self.left[self.right[c]] = c;
+    }
+
+    // Adds row node r to the partial solution and covers its other columns
+    fn select(&mut self, r: usize) {
+        self.chosen.push(r);
+        let mut j = self.right[r];
+        while j != r {
+            self.cover(self.col[j]);
+            j = self.right[j];
+        }
+    }
+
+    // Undoes select for row node r
+    fn deselect(&mut self, r: usize) {
+        let mut j = self.left[r];
+        while j != r {
+            self.uncover(self.col[j]);
+            j = self.left[j];
+        }
+    }
+
+    // The column with the fewest remaining rows
+    fn smallest_column(&self) -> usize {
+        let mut best = 0;
+        let mut c = self.right[0];
+        while c != 0 {
+            if self.size[c] < self.size[best] {
+                best = c;
+            }
+            c = self.right[c];
+        }
+        best
+    }
+
+    // Ids of the rows making up the next exact cover, or None once every
+    // cover has been found or meter has run out. Each node ticks meter.
+    pub(crate) fn next_solution(&mut self, meter: &mut Meter) -> Option<Vec<usize>> {
+        if self.done {
+            return None;
+        }
+        let mut backtracking = self.resume;
+        loop {
+            if backtracking {
+                if self.chosen.len() == self.fixed {
+                    self.done = true;
+                    return None;
+                }
+                let r = self.chosen.pop().expect("chosen holds more than the fixed rows");
+                self.deselect(r);
+                self.stats.backtracks += 1;
+                let c = self.col[r];
+                match self.down[r] {
+                    next if next == c => self.uncover(c),
+                    next => {
+                        self.stats.guesses += 1;
+                        self.select(next);
+                        backtracking = false;
+                    }
+                }
+            } else {
+                if !meter.tick() {
+                    // The next call starts by expanding this node
+                    self.resume = false;
+                    return None;
+                }
+                self.stats.max_depth = self.stats.max_depth.max(self.chosen.len() - self.fixed);
+                if self.right[0] == 0 {
+                    self.resume = true;
+                    return Some(self.chosen.iter().map(|&node| self.row[node]).collect());
+                }
+                let c = self.smallest_column();
+                if self.size[c] == 0 {
+                    self.stats.wipeouts += 1;
+                    backtracking = true;
+                    continue;
+                }
+                if self.size[c] > 1 {
+                    self.stats.guesses += 1;
+                }
+                self.cover(c);
+                self.select(self.down[c]);
+            }
+        }
+    }
+}
+
+// Iterator returned by SudokuBoard::solutions_dlx and solutions_dlx_within
+pub struct DlxSolutions<const BOX_ROWS: usize, const BOX_COLS: usize> {
+    dlx: Option<Dlx>,
+    board: SudokuBoard<BOX_ROWS, BOX_COLS>,
+    budget: Budget,
+    // Nodes visited over every call to next so far
+    nodes: u64,
+    aborted: bool,
+}
+
+impl<const BOX_ROWS: usize, const BOX_COLS: usize> DlxSolutions<BOX_ROWS, BOX_COLS> {
+    // True if the iterator ended because its budget ran out, so there may be
+    // solutions it never returned
+    pub fn is_aborted(&self) -> bool {
+        self.aborted
+    }
+}
+
+impl<const BOX_ROWS: usize, const BOX_COLS: usize> Iterator for DlxSolutions<BOX_ROWS, BOX_COLS> {
+    type Item = SudokuBoard<BOX_ROWS, BOX_COLS>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let dlx = self.dlx.as_mut()?;
+        let mut meter = Meter {
+            budget: &self.budget,
+            nodes: self.nodes,
+            aborted: self.aborted,
+        };
+        let rows = dlx.next_solution(&mut meter);
+        (self.nodes, self.aborted) = (meter.nodes, meter.aborted);
+        let rows = rows?;
+        let mut solution = self.board.clone();
+        solution.fill_dlx_rows(&rows);
+        Some(solution)
+    }
+}
+
+impl<const BOX_ROWS: usize, const BOX_COLS: usize> SudokuBoard<BOX_ROWS, BOX_COLS> {
+    // Exact cover matrix with a column for each tile, and for each value in
+    // each row, column and box. Every value a tile could take is a matrix row,
+    // with id (row * SIZE + col) * SIZE + value - 1, and the rows of filled
+    // tiles are fixed, so the search only branches on empty ones. The board
+    // must be valid.
+    fn dlx_matrix(&self) -> Dlx {
+        let size = Self::SIZE;
+        let mut dlx = Dlx::new(4 * size * size);
+        // Fixing a row covers its columns, so every row goes in first
+        let mut filled = Vec::new();
+        for row in 0..size {
+            for col in 0..size {
+                // Rows come from the filled peers rather than the cached
+                // domain, which may not have been kept up to date
+                let values = match self.board[row][col] {
+                    Tile::Collapsed(val) => Domain::from_value(val).expect("collapsed tiles hold values in range"),
+                    Tile::Uncollapsed(_) => self.peer_domain(row, col),
+                };
+                let b = Self::box_index(row, col);
+                for val in values {
+                    let v = val - 1;
+                    let node = dlx.add_row(
+                        (row * size + col) * size + v,
+                        &[
+                            row * size + col,
+                            size * size + row * size + v,
+                            2 * size * size + col * size + v,
+                            3 * size * size + b * size + v,
+                        ],
+                    );
+                    if let Tile::Collapsed(_) = self.board[row][col] {
+                        filled.push(node);
+                    }
+                }
+            }
+        }
+        for node in filled {
+            dlx.fix_row(node);
+        }
+        dlx
+    }
+
+    // Fills in the tiles of the matrix rows with the given ids
+    fn fill_dlx_rows(&mut self, rows: &[usize]) {
+        let size = Self::SIZE;
+        for &id in rows {
+            let (row, col, val) = (id / (size * size), id / size % size, id % size + 1);
+            self.board[row][col] = Tile::Collapsed(val);
+        }
+    }
+
+    // Solves the board using Dancing Links. Where there are several solutions
+    // it may fill in a different one than solve_csp. Each node fills one
+    // empty tile, and nothing is propagated, so propagations stays 0.
+    pub fn solve_dlx(&mut self) -> Result<SolveStats, SudokuError> {
+        self.solve_dlx_within(&Budget::default())
+    }
+
+    // solve_dlx, stopping with outcome Aborted if budget runs out, in which
+    // case the board is left as it was
+    pub fn solve_dlx_within(&mut self, budget: &Budget) -> Result<SolveStats, SudokuError> {
+        if !self.is_valid() {
+            return Err(SudokuError::InvalidBoard);
+        }
+        let start = Instant::now();
+        let mut meter = Meter::new(budget);
+        let mut dlx = self.dlx_matrix();
+        let rows = dlx.next_solution(&mut meter);
+        let mut stats = dlx.stats;
+        stats.outcome = match rows {
+            Some(rows) => {
+                self.fill_dlx_rows(&rows);
+                SolveOutcome::Solved
+            }
+            None if meter.aborted => SolveOutcome::Aborted,
+            None => SolveOutcome::Unsolvable,
+        };
+        stats.nodes = meter.nodes;
+        stats.elapsed = start.elapsed();
+        Ok(stats)
+    }
+
+    // Same result as count_solutions, using Dancing Links
+    pub fn count_solutions_dlx(&self, limit: usize) -> usize {
+        self.solutions_dlx().take(limit).count()
+    }
+
+    // count_solutions_dlx, or Err(SudokuError::Aborted) if budget runs out
+    // first
+    pub fn count_solutions_dlx_within(&self, limit: usize, budget: &Budget) -> Result<usize, SudokuError> {
+        let mut solutions = self.solutions_dlx_within(budget);
+        let count = solutions.by_ref().take(limit).count();
+        match solutions.is_aborted() {
+            true => Err(SudokuError::Aborted),
+            false => Ok(count),
+        }
+    }
+
+    // Lazily enumerates every solution using Dancing Links
+    pub fn solutions_dlx(&self) -> DlxSolutions<BOX_ROWS, BOX_COLS> {
+        self.solutions_dlx_within(&Budget::default())
+    }
+
+    // solutions_dlx, ending early once the nodes of every call to next
+    // together use up budget
+    pub fn solutions_dlx_within(&self, budget: &Budget) -> DlxSolutions<BOX_ROWS, BOX_COLS> {
+        let dlx = match self.is_valid() {
+            true => Some(self.dlx_matrix()),
+            false => None,
+        };
+        DlxSolutions {
+            dlx,
+            board: self.clone(),
+            budget: budget.clone(),
+            nodes: 0,
+            aborted: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dlx::Dlx;
+    use crate::solver::{Budget, Meter};
+
+    #[test]
+    fn test_exact_cover() {
+        // Knuth's example from the Dancing Links paper, with one solution
+        let rows: [&[usize]; 6] = [
+            &[2, 4, 5],
+            &[0, 3, 6],
+            &[1, 2, 5],
+            &[0, 3],
+            &[1, 6],
+            &[3, 4, 6],
+        ];
+        let budget = Budget::default();
+        let mut meter = Meter::new(&budget);
+        let mut dlx = Dlx::new(7);
+        for (id, columns) in rows.iter().enumerate() {
+            dlx.add_row(id, columns);
+        }
+        let mut solution = dlx.next_solution(&mut meter).unwrap();
+        solution.sort_unstable();
+        assert_eq!(solution, vec![0, 3, 4]);
+        assert!(dlx.next_solution(&mut meter).is_none());
+        assert!(dlx.next_solution(&mut meter).is_none());
+    }
+
+    #[test]
+    fn test_exact_cover_several_solutions() {
+        // Every way to cover 3 columns with single-column rows and a full row
+        let budget = Budget::default();
+        let mut meter = Meter::new(&budget);
+        let mut dlx = Dlx::new(3);
+        dlx.add_row(0, &[0]);
+        dlx.add_row(1, &[1]);
+        dlx.add_row(2, &[2]);
+        dlx.add_row(3, &[0, 1, 2]);
+        dlx.add_row(4, &[0, 1]);
+        let mut count = 0;
+        while dlx.next_solution(&mut meter).is_some() {
+            count += 1;
+        }
+        assert_eq!(count, 3);
+    }
+
+    #[test]
+    fn test_resume_after_budget_runs_out() {
+        // Each call gets one node, so it stops at every node and picks up
+        // where the last one stopped
+        let mut dlx = Dlx::new(3);
+        dlx.add_row(0, &[0]);
+        dlx.add_row(1, &[1]);
+        dlx.add_row(2, &[2]);
+        dlx.add_row(3, &[0, 1, 2]);
+        dlx.add_row(4, &[0, 1]);
+        let budget = Budget { max_nodes: Some(1), ..Budget::default() };
+        let mut count = 0;
+        for _ in 0..100 {
+            let mut meter = Meter::new(&budget);
+            if dlx.next_solution(&mut meter).is_some() {
+                count += 1;
+            }
+        }
+        assert_eq!(count, 3);
+        assert!(dlx.done);
+    }
+
+    #[test]
+    fn test_no_exact_cover() {
+        let budget = Budget::default();
+        let mut meter = Meter::new(&budget);
+        let mut dlx = Dlx::new(2);
+        dlx.add_row(0, &[0]);
+        assert!(dlx.next_solution(&mut meter).is_none());
+    }
+}
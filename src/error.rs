@@ -0,0 +1,47 @@
+use std::fmt;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SudokuError {
+    // from_array was given the wrong number of tiles
+    WrongLength { expected: usize, found: usize },
+    // A tile holds a value larger than the board size
+    InvalidTile { row: usize, col: usize, value: usize },
+    // A value outside 1..=size
+    ValueOutOfRange { value: usize, size: usize },
+    // A tile position off the edge of the board
+    OutOfBounds { row: usize, col: usize },
+    // More filled tiles were asked for than the board has
+    TooManyFilled { requested: usize, max: usize },
+    // Two filled tiles with the same value share a row, column or box
+    InvalidBoard,
+    // A budget ran out before the work was done
+    Aborted,
+}
+
+impl fmt::Display for SudokuError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SudokuError::WrongLength { expected, found } => {
+                write!(f, "expected {} tiles, found {}", expected, found)
+            }
+            SudokuError::InvalidTile { row, col, value } => {
+                write!(f, "tile ({}, {}) holds invalid value {}", row, col, value)
+            }
+            SudokuError::ValueOutOfRange { value, size } => {
+                write!(f, "value {} is outside 1..={}", value, size)
+            }
+            SudokuError::OutOfBounds { row, col } => {
+                write!(f, "tile ({}, {}) is off the board", row, col)
+            }
+            SudokuError::TooManyFilled { requested, max } => {
+                write!(f, "cannot fill {} tiles on a board of {}", requested, max)
+            }
+            SudokuError::InvalidBoard => {
+                write!(f, "a row, column or box contains the same value twice")
+            }
+            SudokuError::Aborted => write!(f, "the budget ran out"),
+        }
+    }
+}
+
+impl std::error::Error for SudokuError {}
@@ -0,0 +1,146 @@
+use crate::board::SudokuBoard;
+use crate::solver::Budget;
+use crate::tile::Tile;
+
+// Which tiles generate_unique_with keeps filled or empty together, so the
+// puzzle's clues form a pattern like those in newspapers
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Symmetry {
+    // Every tile is removed on its own
+    #[default]
+    None,
+    // Turning the board upside down
+    Rotational180,
+    // Mirroring the top half onto the bottom
+    Horizontal,
+    // Mirroring the left half onto the right
+    Vertical,
+    // Mirroring across the diagonal from the top left to the bottom right
+    Diagonal,
+    // All eight rotations and mirrors of the square at once
+    Dihedral,
+}
+
+impl Symmetry {
+    pub const ALL: [Symmetry; 6] = [
+        Symmetry::None,
+        Symmetry::Rotational180,
+        Symmetry::Horizontal,
+        Symmetry::Vertical,
+        Symmetry::Diagonal,
+        Symmetry::Dihedral,
+    ];
+
+    // Every tile this symmetry maps (row, col) to on a board of side size,
+    // (row, col) included, each once
+    pub fn orbit(&self, row: usize, col: usize, size: usize) -> Vec<(usize, usize)> {
+        let (r, c, n) = (row, col, size - 1);
+        let mut orbit = match self {
+            Symmetry::None => vec![(r, c)],
+            Symmetry::Rotational180 => vec![(r, c), (n - r, n - c)],
+            Symmetry::Horizontal => vec![(r, c), (n - r, c)],
+            Symmetry::Vertical => vec![(r, c), (r, n - c)],
+            Symmetry::Diagonal => vec![(r, c), (c, r)],
+            Symmetry::Dihedral => vec![
+                (r, c),
+                (c, n - r),
+                (n - r, n - c),
+                (n - c, r),
+                (n - r, c),
+                (r, n - c),
+                (c, r),
+                (n - c, n - r),
+            ],
+        };
+        orbit.sort_unstable();
+        orbit.dedup();
+        orbit
+    }
+}
+
+// Where a generator gets the full board it empties tiles of
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum SolutionSource {
+    // The first solution with one random tile filled, as generate uses. Quick,
+    // but there are only SIZE^3 such boards.
+    FirstSolution,
+    // generate_solution, a shuffled pattern. Every such board is the same
+    // puzzle up to symmetry.
+    Pattern,
+    // generate_solution_by_search, which can reach any board
+    #[default]
+    Search,
+}
+
+// Settings for generate_unique_with. The default matches generate_unique.
+#[derive(Clone, Debug, Default)]
+pub struct GeneratorOptions {
+    pub source: SolutionSource,
+    pub symmetry: Symmetry,
+    // The nodes of every uniqueness check count towards it
+    pub budget: Budget,
+}
+
+impl<const BOX_ROWS: usize, const BOX_COLS: usize> SudokuBoard<BOX_ROWS, BOX_COLS> {
+    // True if the filled tiles look the same under symmetry
+    pub fn has_symmetry(&self, symmetry: Symmetry) -> bool {
+        (0..Self::SIZE).all(|row| {
+            (0..Self::SIZE).all(|col| {
+                let filled = matches!(self.board[row][col], Tile::Collapsed(_));
+                symmetry
+                    .orbit(row, col, Self::SIZE)
+                    .into_iter()
+                    .all(|(r, c)| matches!(self.board[r][c], Tile::Collapsed(_)) == filled)
+            })
+        })
+    }
+
+    // Groups of tiles that symmetry keeps filled or empty together, each tile
+    // in exactly one
+    pub(crate) fn orbits(symmetry: Symmetry) -> Vec<Vec<(usize, usize)>> {
+        (0..Self::SIZE)
+            .flat_map(|row| (0..Self::SIZE).map(move |col| (row, col)))
+            .map(|(row, col)| (row, col, symmetry.orbit(row, col, Self::SIZE)))
+            // Only the first tile of each orbit lists it
+            .filter(|(row, col, orbit)| orbit[0] == (*row, *col))
+            .map(|(_, _, orbit)| orbit)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::SudokuBoard;
+    use crate::generator::Symmetry;
+
+    #[test]
+    fn test_orbits_partition_the_board() {
+        for symmetry in Symmetry::ALL {
+            let orbits = SudokuBoard::<3>::orbits(symmetry);
+            let mut tiles: Vec<(usize, usize)> = orbits.iter().flatten().copied().collect();
+            tiles.sort_unstable();
+            tiles.dedup();
+            assert_eq!(tiles.len(), 81);
+            assert_eq!(orbits.iter().map(Vec::len).sum::<usize>(), 81);
+            // Every tile of an orbit has that same orbit
+            for orbit in &orbits {
+                for &(row, col) in orbit {
+                    assert_eq!(&symmetry.orbit(row, col, 9), orbit);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_orbit_sizes() {
+        assert_eq!(Symmetry::Rotational180.orbit(4, 4, 9), vec![(4, 4)]);
+        assert_eq!(Symmetry::Rotational180.orbit(0, 1, 9), vec![(0, 1), (8, 7)]);
+        assert_eq!(Symmetry::Horizontal.orbit(0, 1, 9), vec![(0, 1), (8, 1)]);
+        assert_eq!(Symmetry::Vertical.orbit(0, 1, 9), vec![(0, 1), (0, 7)]);
+        assert_eq!(Symmetry::Diagonal.orbit(2, 2, 9), vec![(2, 2)]);
+        assert_eq!(Symmetry::Dihedral.orbit(0, 1, 9).len(), 8);
+        assert_eq!(Symmetry::Dihedral.orbit(0, 0, 9).len(), 4);
+        assert_eq!(Symmetry::Dihedral.orbit(0, 0, 6).len(), 4);
+        assert_eq!(SudokuBoard::<3>::orbits(Symmetry::Dihedral).len(), 15);
+    }
+}
@@ -0,0 +1,191 @@
+use crate::board::SudokuBoard;
+use crate::error::SudokuError;
+use crate::logic::Technique;
+use crate::solver::Budget;
+
+// Score given to puzzles the logic solver gets stuck on. They need techniques
+// beyond the ones implemented, which start around this rating.
+pub const UNSOLVED_SCORE: f32 = 6.0;
+
+// Each repeat of the hardest technique adds this much, up to MAX_REPEAT_BONUS,
+// so the score stays below the next technique's rating
+const REPEAT_BONUS: f32 = 0.05;
+const MAX_REPEAT_BONUS: f32 = 0.15;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Difficulty {
+    Easy,
+    Medium,
+    Hard,
+    Fiendish,
+    Diabolical,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 5] = [
+        Difficulty::Easy,
+        Difficulty::Medium,
+        Difficulty::Hard,
+        Difficulty::Fiendish,
+        Difficulty::Diabolical,
+    ];
+
+    // Scores in [min, max) fall in this tier
+    pub fn score_range(&self) -> (f32, f32) {
+        match self {
+            Difficulty::Easy => (0.0, 2.0),
+            Difficulty::Medium => (2.0, 2.6),
+            Difficulty::Hard => (2.6, 3.6),
+            Difficulty::Fiendish => (3.6, UNSOLVED_SCORE),
+            Difficulty::Diabolical => (UNSOLVED_SCORE, f32::INFINITY),
+        }
+    }
+
+    pub fn from_score(score: f32) -> Self {
+        *Self::ALL
+            .iter()
+            .find(|difficulty| score < difficulty.score_range().1)
+            .unwrap_or(&Difficulty::Diabolical)
+    }
+}
+
+impl Technique {
+    // Sudoku Explainer rating of the technique
+    pub fn rating(&self) -> f32 {
+        match self {
+            Technique::HiddenSingle => 1.5,
+            Technique::NakedSingle => 2.3,
+            Technique::PointingPair => 2.6,
+            Technique::BoxLineReduction => 2.8,
+            Technique::NakedPair => 3.0,
+            Technique::HiddenPair => 3.4,
+            Technique::NakedTriple => 3.6,
+            Technique::HiddenTriple => 4.0,
+            Technique::NakedQuad => 5.0,
+            Technique::HiddenQuad => 5.4,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Grade {
+    pub difficulty: Difficulty,
+    // On the Sudoku Explainer scale: the rating of the hardest technique
+    // needed, plus a small bonus for each time it had to be repeated
+    pub score: f32,
+    pub hardest: Option<Technique>,
+    // Number of steps the logic solver applied
+    pub steps: usize,
+    // False if the logic solver got stuck before filling the board
+    pub solved: bool,
+}
+
+impl<const BOX_ROWS: usize, const BOX_COLS: usize> SudokuBoard<BOX_ROWS, BOX_COLS> {
+    pub fn grade(&self) -> Grade {
+        match self.grade_within(&Budget::default()) {
+            Ok(grade) => grade,
+            Err(_) => unreachable!("an unlimited budget never runs out"),
+        }
+    }
+
+    // grade, or Err(SudokuError::Aborted) if budget runs out before the
+    // logic solver is done. Each search for a step counts as one node.
+    pub fn grade_within(&self, budget: &Budget) -> Result<Grade, SudokuError> {
+        let result = self.solve_logic_within(budget)?;
+        let hardest = result.steps.iter().map(|step| step.technique).max();
+        let solved = result.is_solved();
+
+        let score = match (solved, hardest) {
+            (false, _) => UNSOLVED_SCORE,
+            (true, None) => 0.0,
+            (true, Some(hardest)) => {
+                let repeats = result
+                    .steps
+                    .iter()
+                    .filter(|step| step.technique == hardest)
+                    .count()
+                    - 1;
+                hardest.rating() + (REPEAT_BONUS * repeats as f32).min(MAX_REPEAT_BONUS)
+            }
+        };
+
+        Ok(Grade {
+            difficulty: Difficulty::from_score(score),
+            score,
+            hardest,
+            steps: result.steps.len(),
+            solved,
+        })
+    }
+
+    // Same as grade().difficulty <= target, but gives up as soon as the logic
+    // solver would need a technique rated above target. Steps are found
+    // easiest first and repeats never lift a score into the next tier, so the
+    // techniques left out cannot change the answer.
+    pub(crate) fn grades_at_most(&self, target: Difficulty) -> bool {
+        let (_, max_score) = target.score_range();
+        if max_score > UNSOLVED_SCORE {
+            return true;
+        }
+        let techniques: Vec<Technique> = Technique::ALL
+            .into_iter()
+            .filter(|technique| technique.rating() < max_score)
+            .collect();
+
+        let mut board = self.clone();
+        while let Some(step) = techniques.iter().find_map(|&technique| board.find_step(technique)) {
+            board.apply_step(&step);
+        }
+        board.is_complete() && board.is_valid()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::SudokuBoard;
+    use crate::generator::GeneratorOptions;
+    use crate::grade::{Difficulty, MAX_REPEAT_BONUS, UNSOLVED_SCORE};
+    use crate::logic::Technique;
+    use crate::prng::SplitMix64;
+
+    #[test]
+    fn test_from_score() {
+        assert_eq!(Difficulty::from_score(0.0), Difficulty::Easy);
+        assert_eq!(Difficulty::from_score(1.5), Difficulty::Easy);
+        assert_eq!(Difficulty::from_score(2.3), Difficulty::Medium);
+        assert_eq!(Difficulty::from_score(3.0), Difficulty::Hard);
+        assert_eq!(Difficulty::from_score(5.4), Difficulty::Fiendish);
+        assert_eq!(Difficulty::from_score(UNSOLVED_SCORE), Difficulty::Diabolical);
+        assert_eq!(Difficulty::from_score(11.0), Difficulty::Diabolical);
+    }
+
+    #[test]
+    fn test_ratings_follow_technique_order() {
+        for pair in Technique::ALL.windows(2) {
+            assert!(pair[0].rating() < pair[1].rating());
+        }
+        assert!(Technique::ALL.iter().all(|t| t.rating() + MAX_REPEAT_BONUS < UNSOLVED_SCORE));
+    }
+
+    #[test]
+    fn test_repeats_stay_in_tier() {
+        for technique in Technique::ALL {
+            assert_eq!(
+                Difficulty::from_score(technique.rating()),
+                Difficulty::from_score(technique.rating() + MAX_REPEAT_BONUS),
+            );
+        }
+    }
+    #[test]
+    fn test_grades_at_most_matches_grade() {
+        let mut rng = SplitMix64::new(7);
+        for num_filled in [22, 26, 30, 36] {
+            let (puzzle, _) = SudokuBoard::<3>::generate_unique_with(num_filled, &GeneratorOptions::default(), &mut rng)
+                .unwrap();
+            let difficulty = puzzle.grade().difficulty;
+            for target in Difficulty::ALL {
+                assert_eq!(puzzle.grades_at_most(target), difficulty <= target);
+            }
+        }
+    }
+}
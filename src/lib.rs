@@ -1,4 +1,23 @@
 mod board;
+mod canonical;
+mod dlx;
+mod error;
+mod generator;
+mod grade;
+mod logic;
+mod observer;
+mod prng;
+mod solver;
+mod strategy;
 mod tile;
 
 pub use board::*;
+pub use dlx::*;
+pub use error::*;
+pub use generator::*;
+pub use grade::*;
+pub use logic::*;
+pub use observer::*;
+pub use prng::*;
+pub use solver::*;
+pub use strategy::*;
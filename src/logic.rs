@@ -0,0 +1,620 @@
+use std::fmt;
+
+use crate::board::{SudokuBoard, Unit};
+use crate::error::SudokuError;
+use crate::solver::{Budget, Meter};
+use crate::tile::{Domain, Tile};
+
+// Named solving techniques, in the order the logic solver tries them.
+// Later techniques are harder for a human to spot.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Technique {
+    HiddenSingle,
+    NakedSingle,
+    PointingPair,
+    BoxLineReduction,
+    NakedPair,
+    HiddenPair,
+    NakedTriple,
+    HiddenTriple,
+    NakedQuad,
+    HiddenQuad,
+}
+
+impl Technique {
+    pub const ALL: [Technique; 10] = [
+        Technique::HiddenSingle,
+        Technique::NakedSingle,
+        Technique::PointingPair,
+        Technique::BoxLineReduction,
+        Technique::NakedPair,
+        Technique::HiddenPair,
+        Technique::NakedTriple,
+        Technique::HiddenTriple,
+        Technique::NakedQuad,
+        Technique::HiddenQuad,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Technique::HiddenSingle => "Hidden Single",
+            Technique::NakedSingle => "Naked Single",
+            Technique::PointingPair => "Pointing Pair",
+            Technique::BoxLineReduction => "Box/Line Reduction",
+            Technique::NakedPair => "Naked Pair",
+            Technique::HiddenPair => "Hidden Pair",
+            Technique::NakedTriple => "Naked Triple",
+            Technique::HiddenTriple => "Hidden Triple",
+            Technique::NakedQuad => "Naked Quad",
+            Technique::HiddenQuad => "Hidden Quad",
+        }
+    }
+}
+
+// A single deduction. Tiles are (row, col), placements and eliminations are
+// (row, col, value).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Step {
+    pub technique: Technique,
+    // Units the technique reasons about, empty for a naked single
+    pub units: Vec<Unit>,
+    // Tiles whose candidates justify the step
+    pub cells: Vec<(usize, usize)>,
+    // Candidate values the technique is about
+    pub values: Vec<usize>,
+    pub placements: Vec<(usize, usize, usize)>,
+    pub eliminations: Vec<(usize, usize, usize)>,
+}
+
+// Explains the step in words, with tiles written as r<row>c<col> from 1
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let cells = join(self.cells.iter().map(|&(row, col)| cell_name(row, col)).collect());
+        let values = join(self.values.iter().map(|val| val.to_string()).collect());
+        let name = self.technique.name();
+        match self.technique {
+            Technique::NakedSingle => write!(f, "{}: {} can only be {}", name, cells, values)?,
+            Technique::HiddenSingle => write!(
+                f,
+                "{}: {} can only go in {} within {}",
+                name, values, cells, self.units[0]
+            )?,
+            Technique::PointingPair | Technique::BoxLineReduction => write!(
+                f,
+                "{}: within {}, {} only fits in {}",
+                name, self.units[0], values, self.units[1]
+            )?,
+            Technique::NakedPair | Technique::NakedTriple | Technique::NakedQuad => write!(
+                f,
+                "{}: {} in {} can only be {}",
+                name, cells, self.units[0], values
+            )?,
+            Technique::HiddenPair | Technique::HiddenTriple | Technique::HiddenQuad => write!(
+                f,
+                "{}: {} only fit in {} within {}",
+                name, values, cells, self.units[0]
+            )?,
+        }
+
+        // Group eliminations by value, in the order they first appear
+        let mut removed: Vec<(usize, Vec<String>)> = Vec::new();
+        for &(row, col, val) in &self.eliminations {
+            match removed.iter_mut().find(|(v, _)| *v == val) {
+                Some((_, cells)) => cells.push(cell_name(row, col)),
+                None => removed.push((val, vec![cell_name(row, col)])),
+            }
+        }
+        if !removed.is_empty() {
+            let removed: Vec<String> = removed
+                .into_iter()
+                .map(|(val, cells)| format!("{} from {}", val, join(cells)))
+                .collect();
+            write!(f, ", so remove {}", removed.join("; "))?;
+        }
+        Ok(())
+    }
+}
+
+fn cell_name(row: usize, col: usize) -> String {
+    format!("r{}c{}", row + 1, col + 1)
+}
+
+// "a", "a and b", "a, b and c"
+fn join(mut items: Vec<String>) -> String {
+    match items.pop() {
+        None => String::new(),
+        Some(last) if items.is_empty() => last,
+        Some(last) => format!("{} and {}", items.join(", "), last),
+    }
+}
+
+pub struct LogicSolution<const BOX_ROWS: usize, const BOX_COLS: usize> {
+    // Every step applied, in order
+    pub steps: Vec<Step>,
+    // The board after the last step, with the remaining candidates
+    pub board: SudokuBoard<BOX_ROWS, BOX_COLS>,
+}
+
+impl<const BOX_ROWS: usize, const BOX_COLS: usize> LogicSolution<BOX_ROWS, BOX_COLS> {
+    pub fn is_solved(&self) -> bool {
+        self.board.is_complete() && self.board.is_valid()
+    }
+}
+
+impl<const BOX_ROWS: usize, const BOX_COLS: usize> SudokuBoard<BOX_ROWS, BOX_COLS> {
+    // Applies the easiest available technique until the board is solved or
+    // no technique makes progress
+    pub fn solve_logic(&self) -> LogicSolution<BOX_ROWS, BOX_COLS> {
+        match self.solve_logic_within(&Budget::default()) {
+            Ok(solution) => solution,
+            Err(_) => unreachable!("an unlimited budget never runs out"),
+        }
+    }
+
+    // solve_logic, or Err(SudokuError::Aborted) if budget runs out first.
+    // Each search for a step counts as one node.
+    pub fn solve_logic_within(&self, budget: &Budget) -> Result<LogicSolution<BOX_ROWS, BOX_COLS>, SudokuError> {
+        let mut meter = Meter::new(budget);
+        let mut board = self.clone();
+        let mut steps = Vec::new();
+        loop {
+            if !meter.tick() {
+                return Err(SudokuError::Aborted);
+            }
+            match board.next_step() {
+                Some(step) => {
+                    board.apply_step(&step);
+                    steps.push(step);
+                }
+                None => return Ok(LogicSolution { steps, board }),
+            }
+        }
+    }
+
+    // The step the easiest applicable technique finds, if any
+    pub fn next_step(&self) -> Option<Step> {
+        Technique::ALL
+            .iter()
+            .find_map(|&technique| self.find_step(technique))
+    }
+
+    pub fn apply_step(&mut self, step: &Step) {
+        for &(row, col, val) in &step.eliminations {
+            if let Tile::Uncollapsed(domain) = &mut self.board[row][col] {
+                domain.mark_invalid(val);
+            }
+        }
+        for &(row, col, val) in &step.placements {
+            if let Tile::Uncollapsed(_) = self.board[row][col] {
+                self.board[row][col] = Tile::Collapsed(val);
+                self.propagate_collapse(row, col);
+            }
+        }
+    }
+
+    pub fn find_step(&self, technique: Technique) -> Option<Step> {
+        match technique {
+            Technique::HiddenSingle => self.find_hidden_single(),
+            Technique::NakedSingle => self.find_naked_single(),
+            Technique::PointingPair => self.find_pointing_pair(),
+            Technique::BoxLineReduction => self.find_box_line_reduction(),
+            Technique::NakedPair => self.find_naked_subset(technique, 2),
+            Technique::HiddenPair => self.find_hidden_subset(technique, 2),
+            Technique::NakedTriple => self.find_naked_subset(technique, 3),
+            Technique::HiddenTriple => self.find_hidden_subset(technique, 3),
+            Technique::NakedQuad => self.find_naked_subset(technique, 4),
+            Technique::HiddenQuad => self.find_hidden_subset(technique, 4),
+        }
+    }
+
+    fn domain(&self, row: usize, col: usize) -> Option<Domain<BOX_ROWS, BOX_COLS>> {
+        match self.board[row][col] {
+            Tile::Collapsed(_) => None,
+            Tile::Uncollapsed(domain) => Some(domain),
+        }
+    }
+
+    fn is_placed(&self, unit: Unit, val: usize) -> bool {
+        Self::unit_cells(unit)
+            .into_iter()
+            .any(|(row, col)| matches!(self.board[row][col], Tile::Collapsed(v) if v == val))
+    }
+
+    // Empty tiles in the unit that still allow val
+    fn positions(&self, unit: Unit, val: usize) -> Vec<(usize, usize)> {
+        Self::unit_cells(unit)
+            .into_iter()
+            .filter(|&(row, col)| matches!(self.domain(row, col), Some(domain) if domain.contains(val)))
+            .collect()
+    }
+
+    fn find_naked_single(&self) -> Option<Step> {
+        for row in 0..Self::SIZE {
+            for col in 0..Self::SIZE {
+                if let Some(domain) = self.domain(row, col) {
+                    if let (1, Some(val)) = (domain.len(), domain.first()) {
+                        return Some(Step {
+                            technique: Technique::NakedSingle,
+                            units: Vec::new(),
+                            cells: vec![(row, col)],
+                            values: vec![val],
+                            placements: vec![(row, col, val)],
+                            eliminations: Vec::new(),
+                        });
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    fn find_hidden_single(&self) -> Option<Step> {
+        for unit in Self::units() {
+            for val in 1..=Self::SIZE {
+                if self.is_placed(unit, val) {
+                    continue;
+                }
+                if let [(row, col)] = self.positions(unit, val)[..] {
+                    return Some(Step {
+                        technique: Technique::HiddenSingle,
+                        units: vec![unit],
+                        cells: vec![(row, col)],
+                        values: vec![val],
+                        placements: vec![(row, col, val)],
+                        eliminations: Vec::new(),
+                    });
+                }
+            }
+        }
+        None
+    }
+
+    // A value confined to one row or column of a box can be removed from the
+    // rest of that row or column
+    fn find_pointing_pair(&self) -> Option<Step> {
+        for b in 0..Self::SIZE {
+            for val in 1..=Self::SIZE {
+                let positions = self.positions(Unit::Box(b), val);
+                if positions.len() < 2 {
+                    continue;
+                }
+                let (row, col) = positions[0];
+                let lines = [
+                    (positions.iter().all(|&(r, _)| r == row), Unit::Row(row)),
+                    (positions.iter().all(|&(_, c)| c == col), Unit::Col(col)),
+                ];
+                for (confined, line) in lines {
+                    if !confined {
+                        continue;
+                    }
+                    let eliminations = self.eliminations_outside(line, &positions, val);
+                    if !eliminations.is_empty() {
+                        return Some(Step {
+                            technique: Technique::PointingPair,
+                            units: vec![Unit::Box(b), line],
+                            cells: positions,
+                            values: vec![val],
+                            placements: Vec::new(),
+                            eliminations,
+                        });
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    // A value confined to one box within a row or column can be removed from
+    // the rest of that box
+    fn find_box_line_reduction(&self) -> Option<Step> {
+        let lines = (0..Self::SIZE).map(Unit::Row).chain((0..Self::SIZE).map(Unit::Col));
+        for line in lines {
+            for val in 1..=Self::SIZE {
+                let positions = self.positions(line, val);
+                if positions.len() < 2 {
+                    continue;
+                }
+                let b = Self::box_index(positions[0].0, positions[0].1);
+                if positions.iter().any(|&(row, col)| Self::box_index(row, col) != b) {
+                    continue;
+                }
+                let eliminations = self.eliminations_outside(Unit::Box(b), &positions, val);
+                if !eliminations.is_empty() {
+                    return Some(Step {
+                        technique: Technique::BoxLineReduction,
+                        units: vec![line, Unit::Box(b)],
+                        cells: positions,
+                        values: vec![val],
+                        placements: Vec::new(),
+                        eliminations,
+                    });
+                }
+            }
+        }
+        None
+    }
+
+    // Removes val from every tile of unit except those in keep
+    fn eliminations_outside(
+        &self,
+        unit: Unit,
+        keep: &[(usize, usize)],
+        val: usize,
+    ) -> Vec<(usize, usize, usize)> {
+        self.positions(unit, val)
+            .into_iter()
+            .filter(|cell| !keep.contains(cell))
+            .map(|(row, col)| (row, col, val))
+            .collect()
+    }
+
+    // n tiles of a unit whose candidates together are only n values: no other
+    // tile in the unit can take those values
+    fn find_naked_subset(&self, technique: Technique, n: usize) -> Option<Step> {
+        for unit in Self::units() {
+            let cells: Vec<(usize, usize)> = Self::unit_cells(unit)
+                .into_iter()
+                .filter(|&(row, col)| {
+                    matches!(self.domain(row, col), Some(domain) if (2..=n).contains(&domain.len()))
+                })
+                .collect();
+
+            for subset in combinations(&cells, n) {
+                let values = subset
+                    .iter()
+                    .filter_map(|&(row, col)| self.domain(row, col))
+                    .fold(Domain::empty(), |all, domain| all.union(domain));
+                if values.len() != n {
+                    continue;
+                }
+                let values = values.get_valid();
+
+                let eliminations: Vec<(usize, usize, usize)> = values
+                    .iter()
+                    .flat_map(|&val| self.eliminations_outside(unit, &subset, val))
+                    .collect();
+                if !eliminations.is_empty() {
+                    return Some(Step {
+                        technique,
+                        units: vec![unit],
+                        cells: subset,
+                        values,
+                        placements: Vec::new(),
+                        eliminations,
+                    });
+                }
+            }
+        }
+        None
+    }
+
+    // n values of a unit that only fit in the same n tiles: those tiles can
+    // take no other values
+    fn find_hidden_subset(&self, technique: Technique, n: usize) -> Option<Step> {
+        for unit in Self::units() {
+            let values: Vec<usize> = (1..=Self::SIZE)
+                .filter(|&val| (2..=n).contains(&self.positions(unit, val).len()))
+                .collect();
+
+            for subset in combinations(&values, n) {
+                let mut cells: Vec<(usize, usize)> = subset
+                    .iter()
+                    .flat_map(|&val| self.positions(unit, val))
+                    .collect();
+                cells.sort_unstable();
+                cells.dedup();
+                if cells.len() != n {
+                    continue;
+                }
+
+                let kept: Domain<BOX_ROWS, BOX_COLS> = subset.iter().copied().collect();
+                let eliminations: Vec<(usize, usize, usize)> = cells
+                    .iter()
+                    .filter_map(|&(row, col)| Some((row, col, self.domain(row, col)?)))
+                    .flat_map(|(row, col, domain)| {
+                        domain.difference(kept).into_iter().map(move |val| (row, col, val))
+                    })
+                    .collect();
+                if !eliminations.is_empty() {
+                    return Some(Step {
+                        technique,
+                        units: vec![unit],
+                        cells,
+                        values: subset,
+                        placements: Vec::new(),
+                        eliminations,
+                    });
+                }
+            }
+        }
+        None
+    }
+}
+
+// The next step a player can take, naming the technique, the tiles and
+// candidates it uses and what it places or eliminates. Unlike solving, it only
+// reveals what that one technique justifies. Returns None if the board breaks
+// the rules, is already full, or is beyond every technique.
+pub fn next_hint<const BOX_ROWS: usize, const BOX_COLS: usize>(
+    board: &SudokuBoard<BOX_ROWS, BOX_COLS>,
+) -> Option<Step> {
+    if !board.is_valid() {
+        return None;
+    }
+    board.next_step()
+}
+
+// Every way to pick k items, keeping their original order
+fn combinations<T: Copy>(items: &[T], k: usize) -> Vec<Vec<T>> {
+    if k == 0 {
+        return vec![Vec::new()];
+    }
+    if items.len() < k {
+        return Vec::new();
+    }
+    let mut result: Vec<Vec<T>> = combinations(&items[1..], k - 1)
+        .into_iter()
+        .map(|mut rest| {
+            rest.insert(0, items[0]);
+            rest
+        })
+        .collect();
+    result.extend(combinations(&items[1..], k));
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::SudokuBoard;
+    use crate::logic::{combinations, join, Technique};
+    use crate::tile::Tile;
+
+    type Board = SudokuBoard<3>;
+
+    // An empty board where each listed tile only allows the given values
+    fn board_with_candidates(candidates: &[((usize, usize), &[usize])]) -> Board {
+        let mut board = Board::from_array(&[0; 81]).unwrap();
+        for &((row, col), values) in candidates {
+            if let Tile::Uncollapsed(domain) = &mut board.board[row][col] {
+                for val in (1..=9).filter(|val| !values.contains(val)) {
+                    domain.mark_invalid(val);
+                }
+            }
+        }
+        board
+    }
+
+    // Restricts val to the listed tiles of row 0
+    fn row_0_allowing(val: usize, cols: &[usize]) -> Board {
+        let mut board = Board::from_array(&[0; 81]).unwrap();
+        for col in (0..9).filter(|col| !cols.contains(col)) {
+            if let Tile::Uncollapsed(domain) = &mut board.board[0][col] {
+                domain.mark_invalid(val);
+            }
+        }
+        board
+    }
+
+    #[test]
+    fn test_combinations() {
+        assert_eq!(combinations(&[1, 2, 3], 2), vec![vec![1, 2], vec![1, 3], vec![2, 3]]);
+        assert_eq!(combinations(&[1, 2, 3], 0), vec![Vec::<i32>::new()]);
+        assert!(combinations(&[1, 2], 3).is_empty());
+    }
+
+    #[test]
+    fn test_naked_single() {
+        let board = board_with_candidates(&[((4, 4), &[7])]);
+        let step = board.find_step(Technique::NakedSingle).unwrap();
+        assert_eq!(step.placements, vec![(4, 4, 7)]);
+    }
+
+    #[test]
+    fn test_hidden_single() {
+        let board = row_0_allowing(5, &[4]);
+        let step = board.next_step().unwrap();
+        assert_eq!(step.technique, Technique::HiddenSingle);
+        assert_eq!(step.placements, vec![(0, 4, 5)]);
+    }
+
+    #[test]
+    fn test_pointing_pair() {
+        // 1 only fits in the top row of box 0, so it leaves the rest of row 0
+        let mut board = Board::from_array(&[0; 81]).unwrap();
+        for row in 1..3 {
+            for col in 0..3 {
+                if let Tile::Uncollapsed(domain) = &mut board.board[row][col] {
+                    domain.mark_invalid(1);
+                }
+            }
+        }
+        let step = board.find_step(Technique::PointingPair).unwrap();
+        assert_eq!(step.cells, vec![(0, 0), (0, 1), (0, 2)]);
+        assert_eq!(step.eliminations, (3..9).map(|col| (0, col, 1)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_box_line_reduction() {
+        let board = row_0_allowing(1, &[0, 2]);
+        let step = board.find_step(Technique::BoxLineReduction).unwrap();
+        assert_eq!(step.cells, vec![(0, 0), (0, 2)]);
+        assert_eq!(step.eliminations.len(), 6);
+        assert!(step.eliminations.iter().all(|&(row, col, val)| row > 0 && col < 3 && val == 1));
+    }
+
+    #[test]
+    fn test_naked_pair() {
+        let board = board_with_candidates(&[((0, 0), &[1, 2]), ((0, 5), &[1, 2])]);
+        let step = board.find_step(Technique::NakedPair).unwrap();
+        assert_eq!(step.cells, vec![(0, 0), (0, 5)]);
+        assert_eq!(step.values, vec![1, 2]);
+        assert_eq!(step.eliminations.len(), 14);
+    }
+
+    #[test]
+    fn test_naked_triple() {
+        let board = board_with_candidates(&[
+            ((0, 0), &[1, 2]),
+            ((0, 4), &[2, 3]),
+            ((0, 8), &[1, 3]),
+        ]);
+        assert!(board.find_step(Technique::NakedPair).is_none());
+        let step = board.find_step(Technique::NakedTriple).unwrap();
+        assert_eq!(step.values, vec![1, 2, 3]);
+        assert_eq!(step.eliminations.len(), 18);
+    }
+
+    #[test]
+    fn test_hidden_pair() {
+        let mut board = row_0_allowing(1, &[0, 5]);
+        for col in 1..9 {
+            if col != 5 {
+                if let Tile::Uncollapsed(domain) = &mut board.board[0][col] {
+                    domain.mark_invalid(2);
+                }
+            }
+        }
+        let step = board.find_step(Technique::HiddenPair).unwrap();
+        assert_eq!(step.cells, vec![(0, 0), (0, 5)]);
+        assert_eq!(step.values, vec![1, 2]);
+        assert_eq!(step.eliminations.len(), 14);
+
+        board.apply_step(&step);
+        assert_eq!(board.candidates(0, 0), vec![1, 2]);
+        assert_eq!(board.candidates(0, 5), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_join() {
+        assert_eq!(join(vec![]), "");
+        assert_eq!(join(vec!["1".to_string()]), "1");
+        assert_eq!(join(vec!["1".to_string(), "2".to_string(), "3".to_string()]), "1, 2 and 3");
+    }
+
+    #[test]
+    fn test_step_display() {
+        let step = row_0_allowing(5, &[4]).next_step().unwrap();
+        assert_eq!(step.to_string(), "Hidden Single: 5 can only go in r1c5 within row 1");
+
+        let board = board_with_candidates(&[((4, 4), &[7])]);
+        let step = board.find_step(Technique::NakedSingle).unwrap();
+        assert_eq!(step.to_string(), "Naked Single: r5c5 can only be 7");
+
+        let board = board_with_candidates(&[
+            ((0, 0), &[1, 2]),
+            ((0, 4), &[2, 3]),
+            ((0, 8), &[1, 3]),
+        ]);
+        let step = board.find_step(Technique::NakedTriple).unwrap();
+        assert!(step
+            .to_string()
+            .starts_with("Naked Triple: r1c1, r1c5 and r1c9 in row 1 can only be 1, 2 and 3, so remove 1 from r1c2, "));
+        assert!(step.to_string().ends_with("; 3 from r1c2, r1c3, r1c4, r1c6, r1c7 and r1c8"));
+
+        let step = row_0_allowing(1, &[0, 2]).find_step(Technique::BoxLineReduction).unwrap();
+        assert_eq!(
+            step.to_string(),
+            "Box/Line Reduction: within row 1, 1 only fits in box 1, so remove 1 from r2c1, r2c2, r2c3, r3c1, r3c2 and r3c3"
+        );
+    }
+}
@@ -0,0 +1,48 @@
+use std::thread;
+use std::time::Duration;
+
+use crate::board::SudokuBoard;
+
+// Told about each change a solver makes, in order, for animations, GUIs and
+// logs. Every callback does nothing unless overridden, and () observes
+// nothing at all.
+pub trait SolverObserver<const BOX_ROWS: usize, const BOX_COLS: usize> {
+    // (row, col) was just filled with val, by a guess or by propagation
+    fn on_assign(&mut self, _board: &SudokuBoard<BOX_ROWS, BOX_COLS>, _row: usize, _col: usize, _val: usize) {}
+
+    // val was just ruled out for (row, col)
+    fn on_eliminate(&mut self, _board: &SudokuBoard<BOX_ROWS, BOX_COLS>, _row: usize, _col: usize, _val: usize) {}
+
+    // Trying val at (row, col) led nowhere, and the board is back to how it
+    // was before
+    fn on_backtrack(&mut self, _board: &SudokuBoard<BOX_ROWS, BOX_COLS>, _row: usize, _col: usize, _val: usize) {}
+
+    // The board is full
+    fn on_solution(&mut self, _board: &SudokuBoard<BOX_ROWS, BOX_COLS>) {}
+}
+
+impl<const BOX_ROWS: usize, const BOX_COLS: usize> SolverObserver<BOX_ROWS, BOX_COLS> for () {}
+
+// Redraws the board in the terminal after every assignment, pausing so the
+// search can be watched
+#[derive(Copy, Clone, Debug)]
+pub struct TerminalAnimation {
+    pub delay: Duration,
+}
+
+impl TerminalAnimation {
+    pub fn new(delay_ms: u64) -> Self {
+        Self { delay: Duration::from_millis(delay_ms) }
+    }
+}
+
+impl<const BOX_ROWS: usize, const BOX_COLS: usize> SolverObserver<BOX_ROWS, BOX_COLS> for TerminalAnimation {
+    fn on_assign(&mut self, board: &SudokuBoard<BOX_ROWS, BOX_COLS>, _row: usize, _col: usize, _val: usize) {
+        thread::sleep(self.delay);
+        board.print(true);
+    }
+
+    fn on_solution(&mut self, board: &SudokuBoard<BOX_ROWS, BOX_COLS>) {
+        board.print(true);
+    }
+}
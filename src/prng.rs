@@ -0,0 +1,103 @@
+use rand::{Error, RngCore};
+
+// Steele, Lea and Flood's SplitMix64. Unlike rand's StdRng, the numbers it
+// gives for a seed are fixed, so generate_seeded makes the same puzzle in
+// every version of this crate and on every platform.
+#[derive(Clone, Debug)]
+pub struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+}
+
+impl RngCore for SplitMix64 {
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(8) {
+            let bytes = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+// A uniform number in 0..n. Generation picks numbers with this and shuffle
+// instead of rand's gen_range and SliceRandom, whose results may change
+// between rand versions, so only the numbers rng gives decide the puzzle.
+pub(crate) fn below(rng: &mut impl RngCore, n: usize) -> usize {
+    let n = n as u64;
+    // Drops the 2^64 % n smallest outputs, which a plain % would favour
+    let threshold = n.wrapping_neg() % n;
+    loop {
+        let x = rng.next_u64();
+        if x >= threshold {
+            return (x % n) as usize;
+        }
+    }
+}
+
+// Fisher-Yates shuffle built on below
+pub(crate) fn shuffle<T>(rng: &mut impl RngCore, values: &mut [T]) {
+    for i in (1..values.len()).rev() {
+        values.swap(i, below(rng, i + 1));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::RngCore;
+
+    use crate::prng::{below, shuffle, SplitMix64};
+
+    #[test]
+    fn test_splitmix64_reference_output() {
+        let mut rng = SplitMix64::new(0);
+        assert_eq!(rng.next_u64(), 0xe220_a839_7b1d_cdaf);
+        assert_eq!(rng.next_u64(), 0x6e78_9e6a_a1b9_65f4);
+        assert_eq!(rng.next_u64(), 0x06c4_5d18_8009_454f);
+    }
+
+    #[test]
+    fn test_below_is_in_range_and_covers_it() {
+        let mut rng = SplitMix64::new(7);
+        let mut seen = [false; 9];
+        for _ in 0..1000 {
+            seen[below(&mut rng, 9)] = true;
+        }
+        assert!(seen.iter().all(|&s| s));
+        assert_eq!(below(&mut rng, 1), 0);
+    }
+
+    #[test]
+    fn test_shuffle_is_a_seeded_permutation() {
+        let shuffled = |seed| {
+            let mut values: Vec<usize> = (0..20).collect();
+            shuffle(&mut SplitMix64::new(seed), &mut values);
+            values
+        };
+        let mut sorted = shuffled(3);
+        assert_eq!(shuffled(3), sorted);
+        assert_ne!(shuffled(4), sorted);
+        sorted.sort_unstable();
+        assert_eq!(sorted, (0..20).collect::<Vec<usize>>());
+    }
+}
@@ -0,0 +1,237 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::board::{SudokuBoard, Trail, MAX_SIZE};
+use crate::logic::Technique;
+use crate::observer::SolverObserver;
+use crate::tile::Tile;
+
+// How much reasoning solve_csp_with does at each node of the search before
+// it picks a tile to branch on. Each level adds to the one before.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Propagation {
+    // Only removes each placed value from its peers
+    #[default]
+    ForwardChecking,
+    // Also places values with only one possible tile in a row, column or box
+    HiddenSingles,
+    // Also removes the values of two tiles that share the same two candidates
+    // from the rest of their unit
+    NakedPairs,
+    // Also removes values confined to the crossing of a box and a row or column
+    LockedCandidates,
+}
+
+impl Propagation {
+    pub const ALL: [Propagation; 4] = [
+        Propagation::ForwardChecking,
+        Propagation::HiddenSingles,
+        Propagation::NakedPairs,
+        Propagation::LockedCandidates,
+    ];
+
+    // The logic techniques run to a fixpoint at this level
+    pub fn techniques(&self) -> &'static [Technique] {
+        match self {
+            Propagation::ForwardChecking => &[],
+            Propagation::HiddenSingles => &[Technique::HiddenSingle],
+            Propagation::NakedPairs => &[Technique::HiddenSingle, Technique::NakedPair],
+            Propagation::LockedCandidates => &[
+                Technique::HiddenSingle,
+                Technique::NakedPair,
+                Technique::PointingPair,
+                Technique::BoxLineReduction,
+            ],
+        }
+    }
+}
+
+// Limits on how long a solver or generator may run. Whichever limit is hit
+// first stops it, and the default has none.
+#[derive(Clone, Debug, Default)]
+pub struct Budget {
+    // Search nodes to visit at most. A generator counts the nodes of every
+    // search it runs together.
+    pub max_nodes: Option<u64>,
+    pub deadline: Option<Instant>,
+    // Set from another thread to stop the search at its next node
+    pub cancel: Option<Arc<AtomicBool>>,
+}
+
+impl Budget {
+    // A budget that runs out timeout from now
+    pub fn timeout(timeout: Duration) -> Self {
+        Self {
+            deadline: Some(Instant::now() + timeout),
+            ..Self::default()
+        }
+    }
+
+    // True if a search that has visited nodes nodes must stop now
+    pub fn is_exhausted(&self, nodes: u64) -> bool {
+        self.max_nodes.is_some_and(|max| nodes >= max)
+            || self.deadline.is_some_and(|deadline| Instant::now() >= deadline)
+            || self.cancel.as_ref().is_some_and(|cancel| cancel.load(Ordering::Relaxed))
+    }
+}
+
+// Counts the nodes of one or more searches against a budget, and remembers
+// once it has run out so every search on the way back up stops too
+pub(crate) struct Meter<'a> {
+    pub(crate) budget: &'a Budget,
+    pub(crate) nodes: u64,
+    pub(crate) aborted: bool,
+}
+
+impl<'a> Meter<'a> {
+    pub(crate) fn new(budget: &'a Budget) -> Self {
+        Self { budget, nodes: 0, aborted: false }
+    }
+
+    // Counts one more node, or returns false if the budget has run out
+    pub(crate) fn tick(&mut self) -> bool {
+        if !self.aborted && self.budget.is_exhausted(self.nodes) {
+            self.aborted = true;
+        }
+        if self.aborted {
+            return false;
+        }
+        self.nodes += 1;
+        true
+    }
+}
+
+// Settings for solve_csp_with. The default matches solve_csp.
+#[derive(Clone, Debug, Default)]
+pub struct SolverOptions {
+    pub propagation: Propagation,
+    pub budget: Budget,
+}
+
+// How a solver's search ended
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum SolveOutcome {
+    Solved,
+    // Every possibility was tried and none fills the board
+    #[default]
+    Unsolvable,
+    // The budget ran out first, so the board may or may not have a solution
+    Aborted,
+}
+
+// What a solver did on the way to its answer
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct SolveStats {
+    pub outcome: SolveOutcome,
+    // Search nodes expanded, including the root
+    pub nodes: u64,
+    // Values tried that led to no solution and were taken back
+    pub backtracks: u64,
+    // Tile changes made by propagation rather than by trying a value
+    pub propagations: u64,
+    // Nodes where propagation left a tile with no candidates, or forced a
+    // value that breaks the rules
+    pub wipeouts: u64,
+    // Most values tried at once along one path, 0 if none were needed
+    pub max_depth: usize,
+    // Values tried in a tile that had more than one to choose from
+    pub guesses: u64,
+    pub elapsed: Duration,
+}
+
+impl SolveStats {
+    pub fn is_solved(&self) -> bool {
+        self.outcome == SolveOutcome::Solved
+    }
+}
+
+// What one solve_csp_with search carries from node to node
+pub(crate) struct CspSearch<'a, S, const BOX_ROWS: usize, const BOX_COLS: usize> {
+    pub(crate) options: &'a SolverOptions,
+    pub(crate) strategy: &'a mut S,
+    pub(crate) observer: &'a mut dyn SolverObserver<BOX_ROWS, BOX_COLS>,
+    pub(crate) trail: Trail<BOX_ROWS, BOX_COLS>,
+    pub(crate) meter: Meter<'a>,
+    pub(crate) stats: SolveStats,
+}
+
+// A tile solve_csp_with is branching on. These are kept on an explicit stack
+// rather than the call stack, so deep searches on big boards cannot overflow
+// it.
+pub(crate) struct Branch {
+    pub(crate) row: usize,
+    pub(crate) col: usize,
+    // Candidates in the order to try them, with values[..next] already tried
+    pub(crate) values: [usize; MAX_SIZE],
+    pub(crate) count: usize,
+    pub(crate) next: usize,
+    // The value being searched below, and the trail length from before it
+    // was assigned
+    pub(crate) tried: Option<(usize, usize)>,
+}
+
+impl<const BOX_ROWS: usize, const BOX_COLS: usize> SudokuBoard<BOX_ROWS, BOX_COLS> {
+    // Applies the level's techniques until none of them makes progress,
+    // recording every change on the trail and reporting it to observer.
+    // Returns false if a technique places a value that breaks the rules, so
+    // this branch of the search is dead.
+    pub(crate) fn propagate(
+        &mut self,
+        propagation: Propagation,
+        trail: &mut Trail<BOX_ROWS, BOX_COLS>,
+        observer: &mut dyn SolverObserver<BOX_ROWS, BOX_COLS>,
+    ) -> bool {
+        'fixpoint: loop {
+            for &technique in propagation.techniques() {
+                let step = match self.find_step(technique) {
+                    Some(step) => step,
+                    None => continue,
+                };
+                for &(row, col, val) in &step.eliminations {
+                    self.eliminate(row, col, val, trail, observer);
+                }
+                for &(row, col, val) in &step.placements {
+                    if !self.is_valid_assignment(val, row, col) {
+                        return false;
+                    }
+                    self.assign(row, col, val, trail, observer);
+                }
+                continue 'fixpoint;
+            }
+            return true;
+        }
+    }
+
+    fn eliminate(
+        &mut self,
+        row: usize,
+        col: usize,
+        val: usize,
+        trail: &mut Trail<BOX_ROWS, BOX_COLS>,
+        observer: &mut dyn SolverObserver<BOX_ROWS, BOX_COLS>,
+    ) {
+        if let Tile::Uncollapsed(domain) = &mut self.board[row][col] {
+            if domain.contains(val) {
+                trail.push((row, col, Tile::Uncollapsed(*domain)));
+                domain.mark_invalid(val);
+                observer.on_eliminate(self, row, col, val);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::solver::Propagation;
+
+    #[test]
+    fn test_levels_add_techniques() {
+        assert!(Propagation::default().techniques().is_empty());
+        for pair in Propagation::ALL.windows(2) {
+            assert!(pair[0] < pair[1]);
+            assert!(pair[0].techniques().iter().all(|t| pair[1].techniques().contains(t)));
+            assert!(pair[0].techniques().len() < pair[1].techniques().len());
+        }
+    }
+}
@@ -0,0 +1,220 @@
+use crate::board::SudokuBoard;
+use crate::prng::{below, shuffle, SplitMix64};
+use crate::tile::Tile;
+
+// Decides which empty tile solve_csp_with branches on at each node of the
+// search, and the order it tries that tile's values in
+pub trait SearchStrategy<const BOX_ROWS: usize, const BOX_COLS: usize> {
+    // The empty tile to branch on, or None if the board is full
+    fn select_tile(&mut self, board: &SudokuBoard<BOX_ROWS, BOX_COLS>) -> Option<(usize, usize)>;
+
+    // Reorders the candidates of (row, col), given in increasing order, into
+    // the order to try them. Leaves them increasing by default.
+    fn order_values(
+        &mut self,
+        _board: &SudokuBoard<BOX_ROWS, BOX_COLS>,
+        _row: usize,
+        _col: usize,
+        _values: &mut [usize],
+    ) {
+    }
+}
+
+// Branches on the tile with the fewest candidates, preferring the one with
+// the most empty peers when several tie, then the first scanned
+#[derive(Copy, Clone, Debug, Default)]
+pub struct MinimumRemainingValues;
+
+// Picks tiles like MinimumRemainingValues, and tries first the values that
+// remove the fewest candidates from the tile's peers
+#[derive(Copy, Clone, Debug, Default)]
+pub struct LeastConstrainingValue;
+
+// Picks a tile with the fewest candidates at random and tries its values in a
+// random order. The same seed always gives the same search, in every version
+// of this crate.
+#[derive(Clone, Debug)]
+pub struct RandomOrder {
+    rng: SplitMix64,
+}
+
+impl RandomOrder {
+    pub fn new(seed: u64) -> Self {
+        Self { rng: SplitMix64::new(seed) }
+    }
+}
+
+// Branches on the first empty tile in reading order, as solve_brute_force does
+#[derive(Copy, Clone, Debug, Default)]
+pub struct FirstEmpty;
+
+impl<const BOX_ROWS: usize, const BOX_COLS: usize> SearchStrategy<BOX_ROWS, BOX_COLS> for MinimumRemainingValues {
+    fn select_tile(&mut self, board: &SudokuBoard<BOX_ROWS, BOX_COLS>) -> Option<(usize, usize)> {
+        // (candidates, empty peers, row, col) of the best tile so far
+        let mut best: Option<(usize, usize, usize, usize)> = None;
+        for (row, col, remaining) in board.empty_tiles() {
+            if remaining == 0 {
+                return Some((row, col));
+            }
+            if matches!(best, Some((fewest, ..)) if remaining > fewest) {
+                continue;
+            }
+            let degree = board.count_empty_peers(row, col);
+            match best {
+                Some((fewest, most, ..)) if remaining == fewest && degree <= most => {}
+                _ => best = Some((remaining, degree, row, col)),
+            }
+        }
+        best.map(|(_, _, row, col)| (row, col))
+    }
+}
+
+impl<const BOX_ROWS: usize, const BOX_COLS: usize> SearchStrategy<BOX_ROWS, BOX_COLS> for LeastConstrainingValue {
+    fn select_tile(&mut self, board: &SudokuBoard<BOX_ROWS, BOX_COLS>) -> Option<(usize, usize)> {
+        MinimumRemainingValues.select_tile(board)
+    }
+
+    fn order_values(&mut self, board: &SudokuBoard<BOX_ROWS, BOX_COLS>, row: usize, col: usize, values: &mut [usize]) {
+        values.sort_unstable_by_key(|&val| (board.count_peers_allowing(row, col, val), val));
+    }
+}
+
+impl<const BOX_ROWS: usize, const BOX_COLS: usize> SearchStrategy<BOX_ROWS, BOX_COLS> for RandomOrder {
+    fn select_tile(&mut self, board: &SudokuBoard<BOX_ROWS, BOX_COLS>) -> Option<(usize, usize)> {
+        let mut fewest = usize::MAX;
+        let mut ties = 0;
+        let mut chosen = None;
+        for (row, col, remaining) in board.empty_tiles() {
+            if remaining < fewest {
+                fewest = remaining;
+                ties = 0;
+            }
+            // Keeps each tied tile with equal chance without storing them
+            if remaining == fewest {
+                ties += 1;
+                if below(&mut self.rng, ties) == 0 {
+                    chosen = Some((row, col));
+                }
+            }
+        }
+        chosen
+    }
+
+    fn order_values(&mut self, _board: &SudokuBoard<BOX_ROWS, BOX_COLS>, _row: usize, _col: usize, values: &mut [usize]) {
+        shuffle(&mut self.rng, values);
+    }
+}
+
+impl<const BOX_ROWS: usize, const BOX_COLS: usize> SearchStrategy<BOX_ROWS, BOX_COLS> for FirstEmpty {
+    fn select_tile(&mut self, board: &SudokuBoard<BOX_ROWS, BOX_COLS>) -> Option<(usize, usize)> {
+        board.empty_tiles().next().map(|(row, col, _)| (row, col))
+    }
+}
+
+impl<const BOX_ROWS: usize, const BOX_COLS: usize> SudokuBoard<BOX_ROWS, BOX_COLS> {
+    // Each empty tile in reading order, with how many candidates it has left
+    pub fn empty_tiles(&self) -> impl Iterator<Item = (usize, usize, usize)> + '_ {
+        self.board.iter().enumerate().flat_map(|(row, tiles)| {
+            tiles.iter().enumerate().filter_map(move |(col, tile)| match tile {
+                Tile::Collapsed(_) => None,
+                Tile::Uncollapsed(domain) => Some((row, col, domain.len())),
+            })
+        })
+    }
+
+    // How many empty tiles share a row, column or box with (row, col)
+    pub fn count_empty_peers(&self, row: usize, col: usize) -> usize {
+        self.distinct_peers(row, col)
+            .filter(|&(r, c)| matches!(self.board[r][c], Tile::Uncollapsed(_)))
+            .count()
+    }
+
+    // How many peers of (row, col) still have val as a candidate, so would
+    // lose it if (row, col) took val
+    pub fn count_peers_allowing(&self, row: usize, col: usize, val: usize) -> usize {
+        self.distinct_peers(row, col)
+            .filter(|&(r, c)| matches!(self.board[r][c], Tile::Uncollapsed(domain) if domain.contains(val)))
+            .count()
+    }
+
+    // Every peer of (row, col) once, without allocating like peers does
+    fn distinct_peers(&self, row: usize, col: usize) -> impl Iterator<Item = (usize, usize)> {
+        let box_row = row / BOX_ROWS * BOX_ROWS;
+        let box_col = col / BOX_COLS * BOX_COLS;
+        (0..Self::SIZE)
+            .filter(move |&r| r != row)
+            .map(move |r| (r, col))
+            .chain((0..Self::SIZE).filter(move |&c| c != col).map(move |c| (row, c)))
+            .chain(
+                (0..Self::SIZE)
+                    .map(move |i| (box_row + i / BOX_COLS, box_col + i % BOX_COLS))
+                    .filter(move |&(r, c)| r != row && c != col),
+            )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::SudokuBoard;
+    use crate::strategy::{FirstEmpty, LeastConstrainingValue, MinimumRemainingValues, RandomOrder, SearchStrategy};
+
+    type Board = SudokuBoard<3>;
+
+    const PUZZLE: [usize; 81] = [
+        0, 1, 0, 0, 0, 2, 0, 0, 0, 3, 0, 4, 5, 6, 0, 1, 0, 7, 0, 8, 0, 0, 1, 0, 0, 0, 9, 0, 3, 7, 0, 0,
+        0, 0, 9, 0, 0, 9, 0, 0, 4, 0, 0, 1, 0, 0, 5, 0, 0, 0, 0, 8, 2, 0, 5, 0, 0, 0, 7, 0, 0, 6, 0, 2,
+        0, 1, 0, 9, 3, 5, 0, 8, 0, 0, 0, 1, 0, 0, 0, 4, 0,
+    ];
+
+    #[test]
+    fn test_first_empty() {
+        let board = SudokuBoard::<2>::from_array(&[1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]).unwrap();
+        assert_eq!(FirstEmpty.select_tile(&board), Some((0, 1)));
+        let full = SudokuBoard::<2>::from_array(&[1, 2, 3, 4, 3, 4, 1, 2, 2, 1, 4, 3, 4, 3, 2, 1]).unwrap();
+        assert_eq!(FirstEmpty.select_tile(&full), None);
+        assert_eq!(MinimumRemainingValues.select_tile(&full), None);
+    }
+
+    #[test]
+    fn test_mrv_breaks_ties_by_degree() {
+        let board = Board::from_array(&PUZZLE).unwrap();
+        let (row, col) = MinimumRemainingValues.select_tile(&board).unwrap();
+        let fewest = board.empty_tiles().map(|(_, _, remaining)| remaining).min().unwrap();
+        let tied: Vec<(usize, usize)> = board
+            .empty_tiles()
+            .filter(|&(_, _, remaining)| remaining == fewest)
+            .map(|(r, c, _)| (r, c))
+            .collect();
+        assert!(tied.len() > 1);
+        let most = tied.iter().map(|&(r, c)| board.count_empty_peers(r, c)).max().unwrap();
+        let first_with_most = tied.into_iter().find(|&(r, c)| board.count_empty_peers(r, c) == most);
+        assert_eq!(Some((row, col)), first_with_most);
+    }
+
+    #[test]
+    fn test_least_constraining_value() {
+        let board = Board::from_array(&PUZZLE).unwrap();
+        for (row, col, _) in board.empty_tiles() {
+            let mut values = board.candidates(row, col);
+            LeastConstrainingValue.order_values(&board, row, col, &mut values);
+            let counts: Vec<usize> = values.iter().map(|&val| board.count_peers_allowing(row, col, val)).collect();
+            assert!(counts.windows(2).all(|pair| pair[0] <= pair[1]), "{:?}", counts);
+        }
+        let empty = SudokuBoard::<2>::from_array(&[0; 16]).unwrap();
+        assert_eq!(empty.count_peers_allowing(0, 0, 1), 7);
+        assert_eq!(empty.count_empty_peers(0, 0), 7);
+    }
+
+    #[test]
+    fn test_random_order_is_seeded() {
+        let board = SudokuBoard::<2>::from_array(&[0; 16]).unwrap();
+        let picks = |seed| {
+            let mut strategy = RandomOrder::new(seed);
+            let mut values = [1, 2, 3, 4];
+            strategy.order_values(&board, 0, 0, &mut values);
+            (strategy.select_tile(&board), values)
+        };
+        assert_eq!(picks(7), picks(7));
+        assert!((0..20).any(|seed| picks(seed) != picks(7)));
+    }
+}
@@ -1,57 +1,150 @@
-use crate::board::SUDOKU_SIZE;
+use crate::error::SudokuError;
 
-#[derive(Copy, Clone)]
-pub struct Domain {
-    state: [bool; SUDOKU_SIZE],
+// Set of values a tile may still take, with bit val - 1 set for each value
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Domain<const BOX_ROWS: usize, const BOX_COLS: usize = BOX_ROWS> {
+    bits: u64,
 }
 
-impl Default for Domain {
+impl<const BOX_ROWS: usize, const BOX_COLS: usize> Default for Domain<BOX_ROWS, BOX_COLS> {
     fn default() -> Self {
-        Self {
-            state: [true; SUDOKU_SIZE],
-        }
+        Self { bits: Self::ALL }
     }
 }
 
-impl Domain {
-    pub fn from_value(val: usize) -> Self {
-        let mut domain = Self::default();
-        domain.state[val-1] = true;
-        domain
+impl<const BOX_ROWS: usize, const BOX_COLS: usize> Domain<BOX_ROWS, BOX_COLS> {
+    const SIZE: usize = BOX_ROWS * BOX_COLS;
+    const ALL: u64 = u64::MAX >> (64 - Self::SIZE);
+
+    pub fn empty() -> Self {
+        Self { bits: 0 }
+    }
+
+    pub fn from_value(val: usize) -> Result<Self, SudokuError> {
+        if val == 0 || val > Self::SIZE {
+            return Err(SudokuError::ValueOutOfRange { value: val, size: Self::SIZE });
+        }
+        let mut domain = Self::empty();
+        domain.mark_valid(val);
+        Ok(domain)
     }
 
     pub fn get_valid(&self) -> Vec<usize> {
-        self.state
-            .iter()
-            .enumerate()
-            .filter_map(|(index, is_valid)| match *is_valid {
-                true => Some(index + 1),
-                false => None,
-            })
-            .collect()
+        self.iter().collect()
+    }
+
+    // The valid values in increasing order, without allocating
+    pub fn iter(&self) -> DomainIter {
+        DomainIter { bits: self.bits }
+    }
+
+    pub fn len(&self) -> usize {
+        self.bits.count_ones() as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bits == 0
+    }
+
+    // The smallest valid value
+    pub fn first(&self) -> Option<usize> {
+        self.iter().next()
+    }
+
+    pub fn contains(&self, val: usize) -> bool {
+        self.bits & Self::bit(val) != 0
     }
 
     // Returns true on modify, false if val is already invalid
     pub fn mark_invalid(&mut self, val: usize) -> bool {
-        if !self.state[val-1] {
+        if !self.contains(val) {
             return false;
         }
-        self.state[val-1] = false;
+        self.bits &= !Self::bit(val);
         true
     }
 
     pub fn mark_valid(&mut self, val: usize) {
-        self.state[val-1] = true;
+        self.bits |= Self::bit(val);
+    }
+
+    pub fn union(&self, other: Self) -> Self {
+        Self { bits: self.bits | other.bits }
+    }
+
+    // The values in self but not in other
+    pub fn difference(&self, other: Self) -> Self {
+        Self { bits: self.bits & !other.bits }
+    }
+
+    fn bit(val: usize) -> u64 {
+        1 << (val - 1)
+    }
+}
+
+impl<const BOX_ROWS: usize, const BOX_COLS: usize> FromIterator<usize> for Domain<BOX_ROWS, BOX_COLS> {
+    fn from_iter<I: IntoIterator<Item = usize>>(iter: I) -> Self {
+        let mut domain = Self::empty();
+        for val in iter {
+            domain.mark_valid(val);
+        }
+        domain
+    }
+}
+
+impl<const BOX_ROWS: usize, const BOX_COLS: usize> IntoIterator for Domain<BOX_ROWS, BOX_COLS> {
+    type Item = usize;
+    type IntoIter = DomainIter;
+
+    fn into_iter(self) -> DomainIter {
+        self.iter()
     }
 }
 
+// Iterator over the values of a Domain, returned by Domain::iter
+#[derive(Copy, Clone, Debug)]
+pub struct DomainIter {
+    bits: u64,
+}
+
+impl Iterator for DomainIter {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        if self.bits == 0 {
+            return None;
+        }
+        let val = self.bits.trailing_zeros() as usize + 1;
+        self.bits &= self.bits - 1;
+        Some(val)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.bits.count_ones() as usize;
+        (len, Some(len))
+    }
+}
+
+impl DoubleEndedIterator for DomainIter {
+    fn next_back(&mut self) -> Option<usize> {
+        if self.bits == 0 {
+            return None;
+        }
+        let index = 63 - self.bits.leading_zeros() as usize;
+        self.bits &= !(1 << index);
+        Some(index + 1)
+    }
+}
+
+impl ExactSizeIterator for DomainIter {}
+
 #[derive(Copy, Clone)]
-pub enum Tile {
+pub enum Tile<const BOX_ROWS: usize, const BOX_COLS: usize = BOX_ROWS> {
     Collapsed(usize),
-    Uncollapsed(Domain),
+    Uncollapsed(Domain<BOX_ROWS, BOX_COLS>),
 }
 
-impl Default for Tile {
+impl<const BOX_ROWS: usize, const BOX_COLS: usize> Default for Tile<BOX_ROWS, BOX_COLS> {
     fn default() -> Self {
         Tile::Uncollapsed(Domain::default())
     }
@@ -59,38 +152,84 @@ impl Default for Tile {
 
 #[cfg(test)]
 mod tests {
-    use crate::{board::SUDOKU_SIZE, tile::Domain};
+    use crate::{error::SudokuError, tile::Domain};
 
     #[test]
     fn test_state_none_valid() {
-        let state = Domain {
-            state: [false; SUDOKU_SIZE],
-        };
+        let state = Domain::<3>::empty();
         let valid_in_state = state.get_valid();
         assert_eq!(valid_in_state.len(), 0);
     }
 
     #[test]
     fn test_state_all_valid() {
-        let state = Domain {
-            state: [true; SUDOKU_SIZE],
-        };
+        let state = Domain::<3>::default();
         let valid_in_state = state.get_valid();
         assert_eq!(valid_in_state.len(), 9);
     }
 
     #[test]
     fn test_state_valid_count() {
-        let mut state = Domain {
-            state: [true; SUDOKU_SIZE],
-        };
-        for i in 1..=SUDOKU_SIZE {
+        let mut state = Domain::<3>::default();
+        for i in 1..=9 {
             state.mark_invalid(i);
             let valid_in_state = state.get_valid();
-            assert_eq!(valid_in_state.contains(&i), false)
+            assert!(!valid_in_state.contains(&i))
         }
 
         let valid_in_state = state.get_valid();
         assert_eq!(valid_in_state.len(), 0);
     }
+
+    #[test]
+    fn test_state_all_valid_base_4() {
+        let state = Domain::<4>::default();
+        assert_eq!(state.get_valid(), (1..=16).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_state_all_valid_2x3() {
+        let state = Domain::<2, 3>::default();
+        assert_eq!(state.get_valid(), vec![1, 2, 3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn test_from_value_out_of_range() {
+        assert_eq!(
+            Domain::<3>::from_value(0).err(),
+            Some(SudokuError::ValueOutOfRange { value: 0, size: 9 })
+        );
+        assert!(Domain::<3>::from_value(10).is_err());
+        assert_eq!(Domain::<3>::from_value(9).unwrap().get_valid(), vec![9]);
+    }
+
+    #[test]
+    fn test_len_and_iter() {
+        let mut domain = Domain::<3>::default();
+        domain.mark_invalid(2);
+        domain.mark_invalid(9);
+        assert_eq!(domain.len(), 7);
+        assert_eq!(domain.iter().collect::<Vec<_>>(), vec![1, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(domain.iter().next_back(), Some(8));
+        assert_eq!(domain.first(), Some(1));
+        assert!(!domain.is_empty());
+        assert!(Domain::<3>::empty().is_empty());
+        assert_eq!(Domain::<3>::empty().first(), None);
+    }
+
+    #[test]
+    fn test_set_operations() {
+        let a: Domain<3> = [1, 2, 3].into_iter().collect();
+        let b: Domain<3> = [3, 4].into_iter().collect();
+        assert_eq!(a.union(b).get_valid(), vec![1, 2, 3, 4]);
+        assert_eq!(a.difference(b).get_valid(), vec![1, 2]);
+        assert!(b.difference(b).is_empty());
+    }
+
+    #[test]
+    fn test_state_all_valid_base_6() {
+        let state = Domain::<6>::default();
+        assert_eq!(state.len(), 36);
+        assert_eq!(state.iter().next_back(), Some(36));
+    }
 }
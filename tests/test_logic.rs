@@ -0,0 +1,178 @@
+use sudoku::*;
+
+const SINGLE_SOLN_ARRAY: [usize; 81] = [
+    0, 1, 0, 0, 0, 2, 0, 0, 0, 3, 0, 4, 5, 6, 0, 1, 0, 7, 0, 8, 0, 0, 1, 0, 0, 0, 9, 0, 3, 7, 0, 0,
+    0, 0, 9, 0, 0, 9, 0, 0, 4, 0, 0, 1, 0, 0, 5, 0, 0, 0, 0, 8, 2, 0, 5, 0, 0, 0, 7, 0, 0, 6, 0, 2,
+    0, 1, 0, 9, 3, 5, 0, 8, 0, 0, 0, 1, 0, 0, 0, 4, 0,
+];
+
+// Checks every placement against the unique solution, and that no
+// elimination removes the solution value
+fn assert_steps_sound<const BOX_ROWS: usize, const BOX_COLS: usize>(
+    puzzle: &SudokuBoard<BOX_ROWS, BOX_COLS>,
+) -> LogicSolution<BOX_ROWS, BOX_COLS> {
+    let size = SudokuBoard::<BOX_ROWS, BOX_COLS>::SIZE;
+    let solution = puzzle.solutions().next().unwrap().into_array();
+    let result = puzzle.solve_logic();
+    for step in &result.steps {
+        assert!(!step.placements.is_empty() || !step.eliminations.is_empty());
+        for &(row, col, val) in &step.placements {
+            assert_eq!(solution[row * size + col], val, "{:?}", step);
+        }
+        for &(row, col, val) in &step.eliminations {
+            assert_ne!(solution[row * size + col], val, "{:?}", step);
+        }
+    }
+    assert!(result.board.is_valid());
+    result
+}
+
+#[test]
+fn test_logic_solves_easy_puzzle() {
+    let puzzle = SudokuBoard::<3>::from_array(&SINGLE_SOLN_ARRAY).unwrap();
+    let result = assert_steps_sound(&puzzle);
+    assert!(result.is_solved());
+    assert_eq!(result.board.into_array(), puzzle.solutions().next().unwrap().into_array());
+}
+
+// "Easter Monster", which needs far more than subsets and intersections
+const EASTER_MONSTER_ARRAY: [usize; 81] = [
+    1, 0, 0, 0, 0, 0, 0, 0, 2, 0, 9, 0, 4, 0, 0, 0, 5, 0, 0, 0, 6, 0, 0, 0, 7, 0, 0, 0, 5, 0, 9, 0,
+    3, 0, 0, 0, 0, 0, 0, 0, 7, 0, 0, 0, 0, 0, 0, 0, 8, 5, 0, 0, 4, 0, 7, 0, 0, 0, 0, 0, 6, 0, 0, 0,
+    3, 0, 0, 0, 9, 0, 8, 0, 0, 0, 2, 0, 0, 0, 0, 0, 1,
+];
+
+#[test]
+fn test_logic_stuck_on_hard_puzzle() {
+    let puzzle = SudokuBoard::<3>::from_array(&EASTER_MONSTER_ARRAY).unwrap();
+    let result = assert_steps_sound(&puzzle);
+    assert!(!result.is_solved());
+    assert!(result.board.next_step().is_none());
+    assert!(!result.board.candidates(0, 1).is_empty());
+}
+
+#[test]
+fn test_logic_steps_sound_on_generated_puzzles() {
+    for _ in 0..20 {
+        let (puzzle, _) = SudokuBoard::<3>::generate_unique(24).unwrap();
+        assert_steps_sound(&puzzle);
+    }
+    for _ in 0..5 {
+        let (puzzle, _) = SudokuBoard::<2, 3>::generate_unique(10).unwrap();
+        assert_steps_sound(&puzzle);
+    }
+}
+
+#[test]
+fn test_grade_easy_puzzle() {
+    let grade = SudokuBoard::<3>::from_array(&SINGLE_SOLN_ARRAY).unwrap().grade();
+    assert!(grade.solved);
+    assert!(grade.steps > 0);
+    assert_eq!(grade.difficulty, Difficulty::from_score(grade.score));
+    assert!(grade.score >= grade.hardest.unwrap().rating());
+    assert!(grade.difficulty <= Difficulty::Hard);
+}
+
+#[test]
+fn test_grade_unsolvable_by_logic() {
+    let grade = SudokuBoard::<3>::from_array(&EASTER_MONSTER_ARRAY).unwrap().grade();
+    assert!(!grade.solved);
+    assert_eq!(grade.score, UNSOLVED_SCORE);
+    assert_eq!(grade.difficulty, Difficulty::Diabolical);
+}
+
+#[test]
+fn test_grade_complete_board() {
+    let solution = SudokuBoard::<3>::from_array(&SINGLE_SOLN_ARRAY).unwrap().solutions().next().unwrap();
+    let grade = solution.grade();
+    assert!(grade.solved);
+    assert_eq!(grade.steps, 0);
+    assert_eq!(grade.hardest, None);
+    assert_eq!(grade.difficulty, Difficulty::Easy);
+}
+
+#[test]
+fn test_grade_only_hidden_singles() {
+    // A solved grid with one tile missing only needs a single
+    let mut array = SudokuBoard::<3>::from_array(&SINGLE_SOLN_ARRAY).unwrap()
+        .solutions()
+        .next()
+        .unwrap()
+        .into_array();
+    array[40] = 0;
+    let grade = SudokuBoard::<3>::from_array(&array).unwrap().grade();
+    assert_eq!(grade.hardest, Some(Technique::HiddenSingle));
+    assert_eq!(grade.score, Technique::HiddenSingle.rating());
+    assert_eq!(grade.difficulty, Difficulty::Easy);
+}
+
+#[test]
+fn test_budgeted_logic() {
+    let puzzle = SudokuBoard::<3>::from_array(&SINGLE_SOLN_ARRAY).unwrap();
+    let steps = puzzle.solve_logic().steps.len() as u64;
+    // One node per step, and one more to find there are none left
+    let enough = Budget { max_nodes: Some(steps + 1), ..Budget::default() };
+    let short = Budget { max_nodes: Some(steps), ..Budget::default() };
+
+    let result = puzzle.solve_logic_within(&enough).ok().unwrap();
+    assert!(result.is_solved());
+    assert_eq!(result.steps, puzzle.solve_logic().steps);
+    assert_eq!(puzzle.solve_logic_within(&short).err(), Some(SudokuError::Aborted));
+
+    assert_eq!(puzzle.grade_within(&enough), Ok(puzzle.grade()));
+    assert_eq!(puzzle.grade_within(&short), Err(SudokuError::Aborted));
+}
+
+#[test]
+fn test_generate_with_difficulty() {
+    let mut rng = SplitMix64::new(7);
+    for target in [Difficulty::Easy, Difficulty::Hard] {
+        let generated = SudokuBoard::<3>::generate_with_difficulty_with(target, 200, &GeneratorOptions::default(), &mut rng);
+        let puzzle = generated.unwrap().unwrap();
+        assert!(puzzle.has_unique_solution());
+        assert_eq!(puzzle.grade().difficulty, target);
+    }
+}
+
+#[test]
+fn test_generate_with_difficulty_budget() {
+    assert!(SudokuBoard::<3>::generate_with_difficulty(Difficulty::Medium, 0).is_none());
+}
+
+#[test]
+fn test_next_hint_matches_solution() {
+    let puzzle = SudokuBoard::<3>::from_array(&SINGLE_SOLN_ARRAY).unwrap();
+    let solution = puzzle.solutions().next().unwrap().into_array();
+
+    // Following hints one at a time reaches the solution
+    let mut board = puzzle.clone();
+    while let Some(hint) = next_hint(&board) {
+        assert!(!hint.to_string().is_empty());
+        for &(row, col, val) in &hint.placements {
+            assert_eq!(solution[row * 9 + col], val);
+        }
+        board.apply_step(&hint);
+    }
+    assert_eq!(board.into_array(), solution);
+}
+
+#[test]
+fn test_next_hint_names_cells_and_candidates() {
+    let puzzle = SudokuBoard::<3>::from_array(&SINGLE_SOLN_ARRAY).unwrap();
+    let hint = next_hint(&puzzle).unwrap();
+    assert_eq!(hint.technique, Technique::HiddenSingle);
+    assert_eq!(hint.cells.len(), 1);
+    assert_eq!(hint.values.len(), 1);
+    assert_eq!(hint.units.len(), 1);
+    assert_eq!(hint.placements, vec![(hint.cells[0].0, hint.cells[0].1, hint.values[0])]);
+}
+
+#[test]
+fn test_no_hint_for_broken_board() {
+    let mut array = SINGLE_SOLN_ARRAY;
+    array[0] = 1; // 1 is already in row 1
+    assert!(next_hint(&SudokuBoard::<3>::from_array(&array).unwrap()).is_none());
+
+    let solution = SudokuBoard::<3>::from_array(&SINGLE_SOLN_ARRAY).unwrap().solutions().next().unwrap();
+    assert!(next_hint(&solution).is_none());
+}
@@ -1,55 +1,898 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use rand::RngCore;
 use sudoku::*;
 
+const AI_ESCARGOT: &str = "1....7.9..3..2...8..96..5....53..9...1..8...26....4...3......1..4......7..7...3..";
+
+// A 9x9 board written row by row, with '.' for an empty tile
+fn parse(puzzle: &str) -> SudokuBoard<3> {
+    let array: Vec<usize> = puzzle
+        .chars()
+        .map(|c| c.to_digit(10).unwrap_or(0) as usize)
+        .collect();
+    SudokuBoard::<3>::from_array(&array).unwrap()
+}
+
 #[test]
 fn test_generation() {
-    let mut board = SudokuBoard::generate(21);
+    let mut board = SudokuBoard::<3>::generate(21).unwrap();
     assert!(board.is_valid());
     assert!(!board.is_complete());
 
-    let mut steps = 0;
-    board.solve_brute_force(0, &mut steps);
+    board.solve_brute_force().unwrap();
     assert!(board.is_valid());
     assert!(board.is_complete());
 }
 
+#[test]
+fn test_seeded_generation() {
+    // Puzzle numbers must keep giving the same board, so this may never change
+    let expected = [
+        1, 4, 0, 0, 3, 6, 7, 8, 9, 0, 0, 7, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 5, 0,
+        0, 5, 0, 2, 9, 1, 0, 4, 0, 3, 0, 9, 8, 4, 0, 0, 2, 0, 1, 8, 0, 3, 7, 0, 0, 0, 5, 0, 0, 0, 0, 3, 0, 5, 0,
+        0, 0, 7, 5, 0, 8, 0, 0, 0, 0, 0,
+    ];
+    let board = SudokuBoard::<3>::generate_seeded(30, 12345).unwrap();
+    assert_eq!(board.into_array(), expected.to_vec());
+    assert!(board.is_valid());
+
+    let with_rng = SudokuBoard::<3>::generate_with_rng(30, &mut SplitMix64::new(12345)).unwrap();
+    assert_eq!(with_rng.into_array(), board.into_array());
+    let other = SudokuBoard::<3>::generate_seeded(30, 12346).unwrap();
+    assert_ne!(other.into_array(), board.into_array());
+
+    let rectangular = SudokuBoard::<2, 3>::generate_seeded(12, 1).unwrap();
+    assert_eq!(rectangular.into_array(), SudokuBoard::<2, 3>::generate_seeded(12, 1).unwrap().into_array());
+    assert_eq!(rectangular.into_array().iter().filter(|&&val| val != 0).count(), 12);
+
+    // Any rng works, not just SplitMix64
+    let board = SudokuBoard::<3>::generate_with_rng(30, &mut rand::thread_rng()).unwrap();
+    assert!(board.is_valid());
+}
+
+// How many bands have only 3 different sets of values among the rows of
+// their boxes, as every band of a shuffled pattern does
+fn pattern_bands(board: &SudokuBoard<3>) -> usize {
+    let array = board.into_array();
+    (0..3)
+        .filter(|band| {
+            let mut sets: Vec<usize> = (band * 3..band * 3 + 3)
+                .flat_map(|row| (0..3).map(move |stack| (row, stack)))
+                .map(|(row, stack)| (0..3).map(|c| 1 << array[row * 9 + stack * 3 + c]).sum())
+                .collect();
+            sets.sort_unstable();
+            sets.dedup();
+            sets.len() == 3
+        })
+        .count()
+}
+
+// Pearson's chi-squared statistic of counts against a uniform distribution
+fn chi_squared(counts: &[usize]) -> f64 {
+    let expected = counts.iter().sum::<usize>() as f64 / counts.len() as f64;
+    counts.iter().map(|&count| (count as f64 - expected).powi(2) / expected).sum()
+}
+
+#[test]
+fn test_solution_generators_distribution() {
+    const SAMPLES: usize = 300;
+    // Above this a uniform variable with 8 degrees of freedom only lands 0.1%
+    // of the time
+    const CRITICAL: f64 = 26.12;
+
+    let mut rng = SplitMix64::new(2021);
+    let mut pattern = (0, [0; 9]);
+    let mut search = (0, [0; 9]);
+    for _ in 0..SAMPLES {
+        for (board, (bands, centre)) in [
+            (SudokuBoard::<3>::generate_solution_with_rng(&mut rng), &mut pattern),
+            (SudokuBoard::<3>::generate_solution_by_search_with_rng(&mut rng), &mut search),
+        ] {
+            assert!(board.is_valid() && board.is_complete());
+            *bands += pattern_bands(&board);
+            centre[board.into_array()[40] - 1] += 1;
+        }
+    }
+
+    // Both spread the values evenly
+    assert!(chi_squared(&pattern.1) < CRITICAL, "{:?}", pattern.1);
+    assert!(chi_squared(&search.1) < CRITICAL, "{:?}", search.1);
+    // but only the search reaches boards that are not a shuffled pattern
+    assert_eq!(pattern.0, 3 * SAMPLES);
+    assert!(search.0 < 3 * SAMPLES / 4, "{} pattern bands", search.0);
+
+    assert!(SudokuBoard::<2, 3>::generate_solution().is_complete());
+    assert!(SudokuBoard::<2, 3>::generate_solution_by_search().is_complete());
+}
+
+#[test]
+fn test_generators_use_solution_source() {
+    // Pattern bands in the solution of each generator's puzzle
+    let bands = |source| {
+        let options = GeneratorOptions { source, ..GeneratorOptions::default() };
+        let mut rng = SplitMix64::new(21);
+        let (unique, _) = SudokuBoard::<3>::generate_unique_with(30, &options, &mut rng).unwrap();
+        let minimal = SudokuBoard::<3>::generate_minimal_with(&options, &mut rng).unwrap();
+        let full = SudokuBoard::<3>::generate_with_source(81, source, &mut rng).unwrap();
+        [unique, minimal, full].map(|puzzle| pattern_bands(&puzzle.solutions().next().unwrap()))
+    };
+    assert_eq!(bands(SolutionSource::Pattern), [3; 3]);
+    let search = bands(SolutionSource::Search);
+    assert!(search.iter().all(|&bands| bands < 3), "{:?}", search);
+
+    let options = GeneratorOptions { source: SolutionSource::Search, ..GeneratorOptions::default() };
+    let mut rng = SplitMix64::new(21);
+    let generated = SudokuBoard::<3>::generate_with_difficulty_with(Difficulty::Easy, 100, &options, &mut rng);
+    let puzzle = generated.unwrap().unwrap();
+    assert!(pattern_bands(&puzzle.solutions().next().unwrap()) < 3);
+
+    assert_eq!(
+        SudokuBoard::<3>::generate_with_source(30, SolutionSource::FirstSolution, &mut SplitMix64::new(12345))
+            .unwrap()
+            .into_array(),
+        SudokuBoard::<3>::generate_seeded(30, 12345).unwrap().into_array()
+    );
+}
+
+#[test]
+fn test_default_source_varies_solutions() {
+    // Pattern solutions all share one fingerprint
+    let fingerprints = |options: &GeneratorOptions| {
+        let mut fingerprints: Vec<u64> = (0..4)
+            .map(|seed| {
+                let (puzzle, _) = SudokuBoard::<3>::generate_unique_with(30, options, &mut SplitMix64::new(seed)).unwrap();
+                puzzle.solutions().next().unwrap().fingerprint()
+            })
+            .collect();
+        fingerprints.sort_unstable();
+        fingerprints.dedup();
+        fingerprints.len()
+    };
+    assert_eq!(GeneratorOptions::default().source, SolutionSource::Search);
+    assert!(fingerprints(&GeneratorOptions::default()) > 1);
+    assert_eq!(fingerprints(&GeneratorOptions { source: SolutionSource::Pattern, ..GeneratorOptions::default() }), 1);
+}
+
+#[test]
+fn test_generate_unique() {
+    let options = GeneratorOptions::default();
+    let (board, reached) = SudokuBoard::<3>::generate_unique_with(30, &options, &mut SplitMix64::new(3)).unwrap();
+    assert!(reached);
+    assert!(board.is_valid());
+    assert!(board.has_unique_solution());
+    assert_eq!(board.into_array().iter().filter(|&&val| val != 0).count(), 30);
+}
+
+#[test]
+fn test_generate_unique_unreachable() {
+    // No 9x9 puzzle with fewer than 17 clues has a unique solution
+    let (board, reached) = SudokuBoard::<3>::generate_unique(10).unwrap();
+    assert!(!reached);
+    assert!(board.is_valid());
+    assert!(board.has_unique_solution());
+    assert!(board.into_array().iter().filter(|&&val| val != 0).count() >= 17);
+}
+
+#[test]
+fn test_symmetric_generation() {
+    let mut rng = SplitMix64::new(22);
+    for symmetry in Symmetry::ALL {
+        let options = GeneratorOptions { symmetry, ..GeneratorOptions::default() };
+        let (board, _) = SudokuBoard::<3>::generate_unique_with(32, &options, &mut rng).unwrap();
+        assert!(board.has_symmetry(symmetry), "{:?}", symmetry);
+        assert!(board.has_unique_solution());
+        let filled = board.into_array().iter().filter(|&&val| val != 0).count();
+        assert!((32..60).contains(&filled), "{:?} left {} tiles", symmetry, filled);
+
+        let (board, _) = SudokuBoard::<2, 3>::generate_unique_with(12, &options, &mut rng).unwrap();
+        assert!(board.has_symmetry(symmetry));
+        assert!(board.has_unique_solution());
+    }
+
+    // Pairs of tiles can reach any even count
+    let options = GeneratorOptions { symmetry: Symmetry::Rotational180, ..GeneratorOptions::default() };
+    let (board, reached) = SudokuBoard::<3>::generate_unique_with(36, &options, &mut rng).unwrap();
+    assert!(reached);
+    assert_eq!(board.into_array().iter().filter(|&&val| val != 0).count(), 36);
+
+    let puzzle = parse(AI_ESCARGOT);
+    assert!(puzzle.has_symmetry(Symmetry::None));
+    assert!(!puzzle.has_symmetry(Symmetry::Rotational180));
+    assert!(SudokuBoard::<3>::generate_solution().has_symmetry(Symmetry::Dihedral));
+}
+
+#[test]
+fn test_minimal_puzzles() {
+    let puzzle = parse(AI_ESCARGOT);
+    assert!(puzzle.is_minimal());
+
+    // One clue more than needed
+    let solution = puzzle.solutions().next().unwrap().into_array();
+    let mut array = puzzle.into_array();
+    let empty = array.iter().position(|&val| val == 0).unwrap();
+    array[empty] = solution[empty];
+    assert!(!SudokuBoard::<3>::from_array(&array).unwrap().is_minimal());
+    assert!(!SudokuBoard::<3>::from_array(&solution).unwrap().is_minimal());
+    // Not unique in the first place
+    assert!(!SudokuBoard::<3>::from_array(&[0; 81]).unwrap().is_minimal());
+
+    let mut rng = SplitMix64::new(23);
+    let board = SudokuBoard::<3>::generate_minimal_with(&GeneratorOptions::default(), &mut rng).unwrap();
+    assert!(board.is_minimal());
+    assert!(SudokuBoard::<2, 3>::generate_minimal().is_minimal());
+
+    let options = GeneratorOptions { symmetry: Symmetry::Rotational180, ..GeneratorOptions::default() };
+    let board = SudokuBoard::<3>::generate_minimal_with(&options, &mut rng).unwrap();
+    assert!(board.has_symmetry(Symmetry::Rotational180));
+    assert!(board.has_unique_solution());
+
+    let options = GeneratorOptions {
+        budget: Budget { max_nodes: Some(10), ..Budget::default() },
+        ..GeneratorOptions::default()
+    };
+    assert_eq!(
+        SudokuBoard::<3>::generate_minimal_with(&options, &mut rng).err(),
+        Some(SudokuError::Aborted)
+    );
+}
+
+#[test]
+fn test_generate_from_mask() {
+    let heart: Vec<bool> = concat!(
+        ".XXX.XXX.",
+        "XX.XXX.XX",
+        "X...X...X",
+        "XX.....XX",
+        ".X.....X.",
+        ".XX...XX.",
+        "..XX.XX..",
+        "...X.X...",
+        "....X....",
+    )
+    .chars()
+    .map(|c| c == 'X')
+    .collect();
+
+    let mut rng = SplitMix64::new(24);
+    let puzzle = SudokuBoard::<3>::generate_from_mask_with(&heart, 1000, &Budget::default(), &mut rng)
+        .unwrap()
+        .unwrap();
+    assert!(puzzle.has_unique_solution());
+    let givens: Vec<bool> = puzzle.into_array().iter().map(|&val| val != 0).collect();
+    assert_eq!(givens, heart);
+
+    // Only the top two rows can never be unique
+    let mut sparse = vec![false; 81];
+    sparse[..18].fill(true);
+    assert!(SudokuBoard::<3>::generate_from_mask(&sparse, 20).unwrap().is_none());
+
+    let budget = Budget { max_nodes: Some(5), ..Budget::default() };
+    assert_eq!(
+        SudokuBoard::<3>::generate_from_mask_with(&heart, 1000, &budget, &mut rng).err(),
+        Some(SudokuError::Aborted)
+    );
+    assert_eq!(
+        SudokuBoard::<3>::generate_from_mask(&heart[..80], 1).err(),
+        Some(SudokuError::WrongLength { expected: 81, found: 80 })
+    );
+    let full = SudokuBoard::<2, 3>::generate_from_mask(&[true; 36], 1).unwrap().unwrap();
+    assert!(full.is_complete());
+}
+
+#[test]
+fn test_generate_unique_rectangular() {
+    let options = GeneratorOptions::default();
+    let (board, reached) = SudokuBoard::<2, 3>::generate_unique_with(14, &options, &mut SplitMix64::new(3)).unwrap();
+    assert!(reached);
+    assert!(board.has_unique_solution());
+    assert_eq!(
+        SudokuBoard::<2, 3>::generate_unique(37).err(),
+        Some(SudokuError::TooManyFilled { requested: 37, max: 36 })
+    );
+}
+
+#[test]
+fn test_count_solutions_4x4() {
+    // There are 288 distinct 4x4 sudoku grids
+    let board = SudokuBoard::<2>::from_array(&[0; 16]).unwrap();
+    assert_eq!(board.count_solutions(usize::MAX), 288);
+    assert_eq!(board.count_solutions(10), 10);
+
+    let mut solutions: Vec<Vec<usize>> = board.solutions().map(|s| s.into_array()).collect();
+    assert_eq!(solutions.len(), 288);
+    solutions.sort();
+    solutions.dedup();
+    assert_eq!(solutions.len(), 288);
+}
+
+#[test]
+fn test_solutions_are_complete_and_extend_puzzle() {
+    let board = SudokuBoard::<3>::generate(24).unwrap();
+    let puzzle = board.into_array();
+    for solution in board.solutions().take(50) {
+        assert!(solution.is_valid());
+        assert!(solution.is_complete());
+        for (given, solved) in puzzle.iter().zip(solution.into_array()) {
+            assert!(*given == 0 || *given == solved);
+        }
+    }
+}
+
+#[test]
+fn test_solvers_agree_with_solution_set() {
+    let board = SudokuBoard::<2, 3>::generate(10).unwrap();
+    let solutions: Vec<Vec<usize>> = board.solutions().map(|s| s.into_array()).collect();
+    assert_eq!(solutions.len(), board.count_solutions(usize::MAX));
+
+    let mut csp_board = board.clone();
+    assert!(csp_board.solve_csp().unwrap().is_solved());
+    assert!(solutions.contains(&csp_board.into_array()));
+
+    let mut dfs_board = board.clone();
+    assert!(dfs_board.solve_brute_force().unwrap().is_solved());
+    assert!(solutions.contains(&dfs_board.into_array()));
+
+    // With one solution per puzzle, both solvers must find exactly that one
+    let options = GeneratorOptions::default();
+    let (unique, reached) = SudokuBoard::<3>::generate_unique_with(28, &options, &mut SplitMix64::new(3)).unwrap();
+    assert!(reached);
+    let solution = unique.solutions().next().unwrap().into_array();
+    let mut csp_board = unique.clone();
+    let mut dfs_board = unique.clone();
+    assert!(csp_board.solve_csp().unwrap().is_solved());
+    assert!(dfs_board.solve_brute_force().unwrap().is_solved());
+    assert_eq!(csp_board.into_array(), solution);
+    assert_eq!(dfs_board.into_array(), solution);
+}
+
+#[test]
+fn test_dlx_solver() {
+    let board = SudokuBoard::<2>::from_array(&[0; 16]).unwrap();
+    assert_eq!(board.count_solutions_dlx(usize::MAX), 288);
+    assert_eq!(board.count_solutions_dlx(10), 10);
+
+    let mut solutions: Vec<Vec<usize>> = board.solutions_dlx().map(|s| s.into_array()).collect();
+    solutions.sort();
+    solutions.dedup();
+    assert_eq!(solutions.len(), 288);
+
+    let puzzle = SudokuBoard::<2, 3>::generate_seeded(10, 11).unwrap();
+    let mut expected: Vec<Vec<usize>> = puzzle.solutions().map(|s| s.into_array()).collect();
+    let mut found: Vec<Vec<usize>> = puzzle.solutions_dlx().map(|s| s.into_array()).collect();
+    expected.sort();
+    found.sort();
+    assert_eq!(found, expected);
+
+    let unique = parse(AI_ESCARGOT);
+    let solution = unique.solutions().next().unwrap().into_array();
+    let mut dlx_board = unique.clone();
+    let stats = dlx_board.solve_dlx().unwrap();
+    assert!(stats.is_solved());
+    assert!(stats.guesses > 0 && stats.backtracks > 0 && stats.wipeouts > 0);
+    assert!(stats.max_depth > 0 && stats.max_depth <= 81 - 23);
+    assert_eq!(stats.propagations, 0);
+    assert_eq!(dlx_board.into_array(), solution);
+    assert_eq!(unique.count_solutions_dlx(2), 1);
+
+    let complete = dlx_board.solve_dlx().unwrap();
+    assert_eq!((complete.outcome, complete.nodes, complete.max_depth), (SolveOutcome::Solved, 1, 0));
+}
+
+#[test]
+fn test_dlx_solver_other_bases() {
+    let mut board = pattern_puzzle::<4, 4>(100);
+    assert!(board.solve_dlx().unwrap().is_solved());
+    assert!(board.is_valid() && board.is_complete());
+
+    let mut board = pattern_puzzle::<3, 4>(60);
+    assert!(board.solve_dlx().unwrap().is_solved());
+    assert!(board.is_valid() && board.is_complete());
+}
+
+#[test]
+fn test_dlx_solver_invalid() {
+    let mut array = [0; 81];
+    array[0] = 5;
+    array[1] = 5;
+    let mut board = SudokuBoard::<3>::from_array(&array).unwrap();
+    assert_eq!(board.solve_dlx(), Err(SudokuError::InvalidBoard));
+    assert_eq!(board.count_solutions_dlx(usize::MAX), 0);
+
+    // Valid, but the last tile of the bottom row needs the 9 of (0, 8)
+    let mut array = [0; 81];
+    for col in 0..8 {
+        array[72 + col] = col + 1;
+    }
+    array[8] = 9;
+    let mut board = SudokuBoard::<3>::from_array(&array).unwrap();
+    assert_eq!(board.solve_dlx().unwrap().outcome, SolveOutcome::Unsolvable);
+    assert_eq!(board.into_array(), array.to_vec());
+}
+
+#[test]
+fn test_propagation_levels() {
+    let puzzle = parse(AI_ESCARGOT);
+    let solution = puzzle.solutions().next().unwrap().into_array();
+
+    let mut node_counts = Vec::new();
+    for propagation in Propagation::ALL {
+        let options = SolverOptions { propagation, ..SolverOptions::default() };
+        let mut board = puzzle.clone();
+        let stats = board.solve_csp_with(&options, &mut MinimumRemainingValues, &mut ()).unwrap();
+        assert!(stats.is_solved());
+        assert_eq!(board.into_array(), solution, "{:?}", propagation);
+        node_counts.push(stats.nodes);
+    }
+    assert!(node_counts.windows(2).all(|pair| pair[1] <= pair[0]), "{:?}", node_counts);
+    assert!(node_counts[3] < node_counts[0], "{:?}", node_counts);
+}
+
+#[test]
+fn test_solve_stats() {
+    let puzzle = parse(AI_ESCARGOT);
+
+    let stats = puzzle.clone().solve_csp().unwrap();
+    assert!(stats.is_solved());
+    // Every node but the first comes from trying a value
+    assert!(stats.guesses > 0 && stats.guesses < stats.nodes);
+    assert!(stats.backtracks > 0 && stats.backtracks < stats.nodes);
+    assert!(stats.propagations > 0);
+    assert!(stats.wipeouts > 0);
+    assert!(stats.max_depth > 0 && stats.max_depth <= 81 - 23);
+
+    let stats = SudokuBoard::<2, 3>::generate(12).unwrap().solve_brute_force().unwrap();
+    assert!(stats.is_solved());
+    assert_eq!(stats.guesses, stats.nodes - 1);
+    assert_eq!((stats.propagations, stats.wipeouts), (0, 0));
+
+    let complete = SudokuBoard::<3>::generate(81).unwrap().solve_csp().unwrap();
+    assert!(complete.is_solved());
+    assert_eq!((complete.nodes, complete.max_depth, complete.backtracks), (1, 0, 0));
+}
+
+// Counts each callback and checks the board matches what it reports
+#[derive(Default)]
+struct EventLog {
+    assigns: u64,
+    eliminations: u64,
+    backtracks: u64,
+    solutions: u64,
+}
+
+impl SolverObserver<3, 3> for EventLog {
+    fn on_assign(&mut self, board: &SudokuBoard<3>, row: usize, col: usize, val: usize) {
+        assert_eq!(board.get(row, col), Some(val));
+        self.assigns += 1;
+    }
+
+    fn on_eliminate(&mut self, board: &SudokuBoard<3>, row: usize, col: usize, val: usize) {
+        assert!(!board.candidates(row, col).contains(&val));
+        self.eliminations += 1;
+    }
+
+    fn on_backtrack(&mut self, board: &SudokuBoard<3>, row: usize, col: usize, _val: usize) {
+        assert_eq!(board.get(row, col), None);
+        self.backtracks += 1;
+    }
+
+    fn on_solution(&mut self, board: &SudokuBoard<3>) {
+        assert!(board.is_complete() && board.is_valid());
+        self.solutions += 1;
+    }
+}
+
+#[test]
+fn test_solver_observer() {
+    let puzzle = parse(AI_ESCARGOT);
+    for propagation in Propagation::ALL {
+        let mut log = EventLog::default();
+        let options = SolverOptions { propagation, ..SolverOptions::default() };
+        let stats = puzzle.clone().solve_csp_with(&options, &mut MinimumRemainingValues, &mut log).unwrap();
+        assert_eq!(log.solutions, 1);
+        assert_eq!(log.backtracks, stats.backtracks);
+        assert!(log.assigns >= stats.nodes - 1);
+        assert!(log.eliminations > 0);
+    }
+
+    let mut log = EventLog::default();
+    let stats = puzzle.clone().solve_brute_force_with(&Budget::default(), &mut log).unwrap();
+    assert_eq!((log.solutions, log.eliminations), (1, 0));
+    assert_eq!(log.assigns, stats.guesses);
+    assert_eq!(log.backtracks, stats.backtracks);
+}
+
+#[test]
+fn test_propagation_unsolvable() {
+    // Valid so far, but nothing can go in the top left tile
+    let mut array = [0; 81];
+    array[1..9].copy_from_slice(&[1, 2, 3, 4, 5, 6, 7, 8]);
+    array[9 * 4] = 9;
+    for propagation in Propagation::ALL {
+        let mut board = SudokuBoard::<3>::from_array(&array).unwrap();
+        let options = SolverOptions { propagation, ..SolverOptions::default() };
+        let stats = board.solve_csp_with(&options, &mut MinimumRemainingValues, &mut ()).unwrap();
+        assert_eq!(stats.outcome, SolveOutcome::Unsolvable);
+        // Every value tried was taken back
+        assert_eq!(stats.backtracks, stats.nodes - 1);
+        assert_eq!(board.into_array(), array.to_vec());
+    }
+}
+
+// Branches on the last empty tile, to check strategies can live outside the
+// crate
+struct LastEmpty;
+
+impl SearchStrategy<3, 3> for LastEmpty {
+    fn select_tile(&mut self, board: &SudokuBoard<3>) -> Option<(usize, usize)> {
+        board.empty_tiles().last().map(|(row, col, _)| (row, col))
+    }
+
+    fn order_values(&mut self, _board: &SudokuBoard<3>, _row: usize, _col: usize, values: &mut [usize]) {
+        values.reverse();
+    }
+}
+
+// The solved board and number of search nodes
+fn solve_with(puzzle: &SudokuBoard<3>, strategy: &mut impl SearchStrategy<3, 3>) -> (Vec<usize>, u64) {
+    let mut board = puzzle.clone();
+    let stats = board.solve_csp_with(&SolverOptions::default(), strategy, &mut ()).unwrap();
+    assert!(stats.is_solved());
+    (board.into_array(), stats.nodes)
+}
+
+#[test]
+fn test_search_strategies() {
+    let (puzzle, _) = SudokuBoard::<3>::generate_unique(26).unwrap();
+    let solution = puzzle.solutions().next().unwrap().into_array();
+
+    assert_eq!(solve_with(&puzzle, &mut MinimumRemainingValues).0, solution);
+    assert_eq!(solve_with(&puzzle, &mut LeastConstrainingValue).0, solution);
+    assert_eq!(solve_with(&puzzle, &mut RandomOrder::new(1)).0, solution);
+    assert_eq!(solve_with(&puzzle, &mut FirstEmpty).0, solution);
+    assert_eq!(solve_with(&puzzle, &mut LastEmpty).0, solution);
+
+    // The same seed searches the same way
+    assert_eq!(solve_with(&puzzle, &mut RandomOrder::new(9)), solve_with(&puzzle, &mut RandomOrder::new(9)));
+}
+
+#[test]
+fn test_budget_node_limit() {
+    let puzzle = parse(AI_ESCARGOT);
+    let budget = Budget { max_nodes: Some(10), ..Budget::default() };
+    let options = SolverOptions { budget: budget.clone(), ..SolverOptions::default() };
+
+    let mut board = puzzle.clone();
+    let stats = board.solve_csp_with(&options, &mut MinimumRemainingValues, &mut ()).unwrap();
+    assert_eq!(stats.outcome, SolveOutcome::Aborted);
+    assert_eq!(stats.nodes, 10);
+    assert_eq!(board.into_array(), puzzle.into_array());
+
+    let mut board = puzzle.clone();
+    let stats = board.solve_brute_force_with(&budget, &mut ()).unwrap();
+    assert_eq!(stats.outcome, SolveOutcome::Aborted);
+    assert_eq!(stats.nodes, 10);
+    assert_eq!(board.into_array(), puzzle.into_array());
+
+    // Enough nodes to finish
+    let options = SolverOptions {
+        budget: Budget { max_nodes: Some(100_000), ..Budget::default() },
+        ..SolverOptions::default()
+    };
+    let mut board = puzzle.clone();
+    assert!(board.solve_csp_with(&options, &mut MinimumRemainingValues, &mut ()).unwrap().is_solved());
+    assert!(board.is_complete());
+}
+
+// Candidates of every tile, row by row
+fn all_candidates<const BOX_ROWS: usize, const BOX_COLS: usize>(board: &SudokuBoard<BOX_ROWS, BOX_COLS>) -> Vec<Vec<usize>> {
+    let size = BOX_ROWS * BOX_COLS;
+    (0..size * size).map(|index| board.candidates(index / size, index % size)).collect()
+}
+
+#[test]
+fn test_brute_force_restores_candidates() {
+    // Only 4 fits the bottom right tile but its column already has one
+    let mut array = [0; 16];
+    array[3] = 4;
+    array[12..15].copy_from_slice(&[1, 2, 3]);
+    let puzzle = SudokuBoard::<2>::from_array(&array).unwrap();
+    let mut board = puzzle.clone();
+    let stats = board.solve_brute_force().unwrap();
+    assert_eq!(stats.outcome, SolveOutcome::Unsolvable);
+    assert!(stats.backtracks > 0);
+    assert_eq!(all_candidates(&board), all_candidates(&puzzle));
+
+    let puzzle = parse(AI_ESCARGOT);
+    let budget = Budget { max_nodes: Some(1000), ..Budget::default() };
+    let mut board = puzzle.clone();
+    let stats = board.solve_brute_force_with(&budget, &mut ()).unwrap();
+    assert_eq!(stats.outcome, SolveOutcome::Aborted);
+    assert!(stats.backtracks > 0);
+    assert_eq!(all_candidates(&board), all_candidates(&puzzle));
+}
+
+#[test]
+fn test_budget_deadline_and_cancel() {
+    let puzzle = parse(AI_ESCARGOT);
+    let passed = Budget::timeout(Duration::ZERO);
+    let cancel = Arc::new(AtomicBool::new(true));
+    let cancelled = Budget { cancel: Some(cancel.clone()), ..Budget::default() };
+    for budget in [passed, cancelled] {
+        let options = SolverOptions { budget: budget.clone(), ..SolverOptions::default() };
+        let stats = puzzle.clone().solve_csp_with(&options, &mut MinimumRemainingValues, &mut ()).unwrap();
+        assert_eq!(stats.outcome, SolveOutcome::Aborted);
+        assert_eq!(stats.nodes, 0);
+        let stats = puzzle.clone().solve_brute_force_with(&budget, &mut ()).unwrap();
+        assert_eq!(stats.outcome, SolveOutcome::Aborted);
+    }
+
+    // Cancelled from another thread while the search runs. Only 9 fits the
+    // bottom right tile but its column already has one, which brute force
+    // never finds out before filling every row above.
+    let mut array = [0; 81];
+    array[8] = 9;
+    array[72..80].copy_from_slice(&[1, 2, 3, 4, 5, 6, 7, 8]);
+    let puzzle = SudokuBoard::<3>::from_array(&array).unwrap();
+    let cancel = Arc::new(AtomicBool::new(false));
+    let budget = Budget { cancel: Some(cancel.clone()), ..Budget::default() };
+    let canceller = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(20));
+        cancel.store(true, Ordering::Relaxed);
+    });
+    let mut board = puzzle.clone();
+    let stats = board.solve_brute_force_with(&budget, &mut ()).unwrap();
+    canceller.join().unwrap();
+    assert_eq!(stats.outcome, SolveOutcome::Aborted);
+    assert_eq!(board.into_array(), puzzle.into_array());
+}
+
+#[test]
+fn test_budgeted_counting() {
+    let puzzle = parse(AI_ESCARGOT);
+    let tiny = Budget { max_nodes: Some(10), ..Budget::default() };
+
+    assert_eq!(puzzle.count_solutions_within(2, &tiny), Err(SudokuError::Aborted));
+    assert_eq!(puzzle.count_solutions_within(2, &Budget::default()), Ok(1));
+    assert_eq!(puzzle.count_solutions_dlx_within(2, &tiny), Err(SudokuError::Aborted));
+    assert_eq!(puzzle.count_solutions_dlx_within(2, &Budget::default()), Ok(1));
+    assert_eq!(puzzle.is_minimal_within(&tiny), Err(SudokuError::Aborted));
+    assert_eq!(puzzle.is_minimal_within(&Budget::default()), Ok(puzzle.is_minimal()));
+
+    let mut board = puzzle.clone();
+    let stats = board.solve_dlx_within(&tiny).unwrap();
+    assert_eq!((stats.outcome, stats.nodes), (SolveOutcome::Aborted, 10));
+    assert_eq!(board.into_array(), puzzle.into_array());
+    assert!(board.solve_dlx_within(&Budget::default()).unwrap().is_solved());
+
+    // The nodes of every call to next count towards one budget
+    let empty = SudokuBoard::<2>::from_array(&[0; 16]).unwrap();
+    let mut solutions = empty.solutions_dlx_within(&Budget { max_nodes: Some(100), ..Budget::default() });
+    let found = solutions.by_ref().count();
+    assert!(found > 0 && found < 288, "{} solutions", found);
+    assert!(solutions.is_aborted());
+    let mut solutions = empty.solutions_dlx();
+    assert_eq!(solutions.by_ref().count(), 288);
+    assert!(!solutions.is_aborted());
+}
+
+#[test]
+fn test_budgeted_generation() {
+    let budget = Budget { max_nodes: Some(50), ..Budget::default() };
+    assert_eq!(SudokuBoard::<3>::generate_unique_within(24, &budget).err(), Some(SudokuError::Aborted));
+    assert_eq!(
+        SudokuBoard::<3>::generate_with_difficulty_within(Difficulty::Hard, 10, &budget).err(),
+        Some(SudokuError::Aborted)
+    );
+
+    let generated = SudokuBoard::<3>::generate_unique_within(30, &Budget::timeout(Duration::from_secs(60)));
+    let (board, reached) = generated.unwrap();
+    assert!(reached);
+    assert!(board.has_unique_solution());
+}
+
+#[test]
+fn test_deep_search_on_small_stack() {
+    // Every empty tile of a 16x16 board is one level deeper, which used to
+    // overflow a thread stack this small
+    let search = thread::Builder::new()
+        .stack_size(64 * 1024)
+        .spawn(|| {
+            let empty = SudokuBoard::<4>::from_array(&[0; 256]).unwrap();
+            let mut csp = empty.clone();
+            let stats = csp.solve_csp().unwrap();
+            assert!(stats.is_solved());
+            assert!(csp.is_valid() && csp.is_complete());
+            let mut brute_force = empty.clone();
+            assert!(brute_force.solve_brute_force().unwrap().is_solved());
+            assert!(brute_force.is_valid() && brute_force.is_complete());
+            assert_eq!(empty.count_solutions(2), 2);
+            stats.max_depth
+        })
+        .unwrap();
+    assert!(search.join().unwrap() > 200);
+}
+
+// The same puzzle in disguise: values relabelled, bands, stacks and the
+// lines within them shuffled, and maybe transposed
+fn disguise(array: &[usize], rng: &mut SplitMix64) -> Vec<usize> {
+    let mut shuffled = |n: usize| {
+        let mut values: Vec<usize> = (0..n).collect();
+        for i in (1..n).rev() {
+            values.swap(i, (rng.next_u64() % (i as u64 + 1)) as usize);
+        }
+        values
+    };
+    let mut lines = || -> Vec<usize> {
+        let outer = shuffled(3);
+        outer.into_iter().flat_map(|band| shuffled(3).into_iter().map(move |line| band * 3 + line)).collect()
+    };
+    let (rows, cols) = (lines(), lines());
+    let relabel = shuffled(9);
+    let transpose = rng.next_u64() & 1 == 1;
+    (0..81)
+        .map(|i| {
+            let (row, col) = match transpose {
+                true => (cols[i % 9], rows[i / 9]),
+                false => (rows[i / 9], cols[i % 9]),
+            };
+            match array[row * 9 + col] {
+                0 => 0,
+                val => relabel[val - 1] + 1,
+            }
+        })
+        .collect()
+}
+
+#[test]
+fn test_canonical_form() {
+    let puzzle = parse(AI_ESCARGOT);
+    let canonical = puzzle.canonical_form();
+    assert_eq!(canonical.canonical_form().into_array(), canonical.into_array());
+    assert_eq!(canonical.count_solutions(2), 1);
+    // Still the same givens
+    let givens = |array: Vec<usize>| array.iter().filter(|&&val| val != 0).count();
+    assert_eq!(givens(canonical.into_array()), givens(puzzle.into_array()));
+
+    let mut rng = SplitMix64::new(25);
+    for _ in 0..5 {
+        let disguised = SudokuBoard::<3>::from_array(&disguise(&puzzle.into_array(), &mut rng)).unwrap();
+        assert_eq!(disguised.canonical_form().into_array(), canonical.into_array());
+        assert_eq!(disguised.fingerprint(), puzzle.fingerprint());
+    }
+
+    // A full board has its first row in order
+    let solution = puzzle.solutions().next().unwrap();
+    assert_eq!(solution.canonical_form().into_array()[..9], [1, 2, 3, 4, 5, 6, 7, 8, 9]);
+    let disguised = SudokuBoard::<3>::from_array(&disguise(&solution.into_array(), &mut rng)).unwrap();
+    assert_eq!(disguised.fingerprint(), solution.fingerprint());
+
+    // A different puzzle, and the same one with a clue more, are not the same
+    let other = SudokuBoard::<3>::generate_seeded(30, 25).unwrap();
+    assert_ne!(other.fingerprint(), puzzle.fingerprint());
+    let mut array = puzzle.into_array();
+    array[1] = solution.into_array()[1];
+    assert_ne!(SudokuBoard::<3>::from_array(&array).unwrap().fingerprint(), puzzle.fingerprint());
+
+    // Stored fingerprints must keep matching
+    assert_eq!(puzzle.fingerprint(), 10041261542622813318);
+
+    let rectangular = SudokuBoard::<2, 3>::generate_seeded(14, 25).unwrap();
+    assert_eq!(rectangular.canonical_form().canonical_form().into_array(), rectangular.canonical_form().into_array());
+}
+
 #[test]
 fn test_brute_force_solver() {
-    let mut board = SudokuBoard::generate(21);
+    let mut board = SudokuBoard::<3>::generate(21).unwrap();
     assert!(board.is_valid());
     assert!(!board.is_complete());
 
-    let mut steps = 0;
-    board.solve_brute_force(0, &mut steps);
+    board.solve_brute_force().unwrap();
     assert!(board.is_complete());
 }
 
 
 #[test]
 fn test_csp_solver() {
-    let mut board = SudokuBoard::generate(21);
+    let mut board = SudokuBoard::<3>::generate(21).unwrap();
     assert!(board.is_valid());
     assert!(!board.is_complete());
 
-    let mut steps = 0;
-    board.solve_csp(0, &mut steps);
+    board.solve_csp().unwrap();
     assert!(board.is_complete());
 }
 
 #[test]
 fn test_csp_is_better() {
-    let mut dfs_board = SudokuBoard::generate(21);
+    // A fixed puzzle, since on some random ones the CSP search visits more
+    // nodes than brute force
+    let mut dfs_board = parse(AI_ESCARGOT);
     assert!(dfs_board.is_valid());
     assert!(!dfs_board.is_complete());
 
     let mut csp_board = dfs_board.clone();
 
-    let mut dfs_steps = 0;
-    dfs_board.solve_brute_force(0, &mut dfs_steps);
+    let dfs_steps = dfs_board.solve_brute_force().unwrap().nodes;
     assert!(dfs_board.is_complete());
 
-    let mut csp_steps = 0;
-    csp_board.solve_csp(0, &mut csp_steps);
+    let csp_steps = csp_board.solve_csp().unwrap().nodes;
     assert!(csp_board.is_complete());
 
     assert!(csp_steps <= dfs_steps);
 }
+
+fn generate_and_solve_csp<const BOX_ROWS: usize, const BOX_COLS: usize>(num_filled: usize) {
+    let mut board = SudokuBoard::<BOX_ROWS, BOX_COLS>::generate(num_filled).unwrap();
+    assert!(board.is_valid());
+    assert!(!board.is_complete());
+
+    assert!(board.solve_csp().unwrap().is_solved());
+    assert!(board.is_valid());
+    assert!(board.is_complete());
+}
+
+// generate fills its board with solve_csp from a single tile, which can take
+// a long time from 12x12 up, so larger tests start from a fixed valid board.
+// 37 is coprime to every tile count, so exactly num_filled tiles are kept.
+fn pattern_puzzle<const BOX_ROWS: usize, const BOX_COLS: usize>(num_filled: usize) -> SudokuBoard<BOX_ROWS, BOX_COLS> {
+    let size = BOX_ROWS * BOX_COLS;
+    let array: Vec<usize> = (0..size * size)
+        .map(|index| {
+            let (r, c) = (index / size, index % size);
+            match index * 37 % (size * size) < num_filled {
+                true => (BOX_COLS * (r % BOX_ROWS) + r / BOX_ROWS + c) % size + 1,
+                false => 0,
+            }
+        })
+        .collect();
+    SudokuBoard::from_array(&array).unwrap()
+}
+
+fn pattern_and_solve_csp<const BOX_ROWS: usize, const BOX_COLS: usize>(num_filled: usize) {
+    let mut board = pattern_puzzle::<BOX_ROWS, BOX_COLS>(num_filled);
+    assert!(board.is_valid());
+    assert!(!board.is_complete());
+
+    assert!(board.solve_csp().unwrap().is_solved());
+    assert!(board.is_valid());
+    assert!(board.is_complete());
+}
+
+#[test]
+fn test_csp_solver_other_bases() {
+    generate_and_solve_csp::<2, 2>(6);
+    pattern_and_solve_csp::<4, 4>(150);
+    pattern_and_solve_csp::<5, 5>(400);
+    pattern_and_solve_csp::<6, 6>(900);
+}
+
+#[test]
+fn test_csp_solver_rectangular_boxes() {
+    generate_and_solve_csp::<2, 3>(12);
+    generate_and_solve_csp::<3, 2>(12);
+    generate_and_solve_csp::<2, 4>(30);
+    pattern_and_solve_csp::<3, 4>(80);
+}
+
+#[test]
+fn test_brute_force_solver_2x3() {
+    let mut board = SudokuBoard::<2, 3>::generate(14).unwrap();
+    assert!(board.is_valid());
+
+    assert!(board.solve_brute_force().unwrap().is_solved());
+    assert!(board.is_valid());
+    assert!(board.is_complete());
+}
+
+#[test]
+fn test_brute_force_solver_base_2() {
+    let mut board = SudokuBoard::<2>::generate(4).unwrap();
+    assert!(board.is_valid());
+
+    assert!(board.solve_brute_force().unwrap().is_solved());
+    assert!(board.is_valid());
+    assert!(board.is_complete());
+}